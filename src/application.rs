@@ -23,8 +23,12 @@ use log::debug;
 use std::sync::Mutex;
 
 use crate::config;
-use crate::ui::{FurtheranceWindow, FurPreferencesWindow, FurReport};
+use crate::ui::{window, FurtheranceWindow, FurPreferencesWindow, FurReport};
 use crate::database;
+use crate::dbus_service;
+use crate::search_provider;
+use crate::import;
+use crate::logind;
 use crate::settings_manager;
 
 mod imp {
@@ -47,21 +51,50 @@ mod imp {
             self.parent_constructed(obj);
 
             obj.setup_gactions();
+            obj.setup_command_line();
             obj.setup_application();
             obj.set_accels_for_action("app.quit", &["<primary>Q", "<primary>W"]);
         }
     }
 
     impl ApplicationImpl for FurtheranceApplication {
+        fn startup(&self, application: &Self::Type) {
+            self.parent_startup(application);
+            // The search provider and D-Bus calls can come in before a window is opened
+            database::init();
+            dbus_service::register(application);
+            search_provider::register(application);
+            logind::watch(application);
+        }
+
+        // Launching Furtherance again runs this in the first instance
+        fn command_line(&self, application: &Self::Type, command_line: &gio::ApplicationCommandLine) -> i32 {
+            let options = command_line.options_dict();
+            if let Ok(Some(task_text)) = options.lookup::<String>("start") {
+                if let Some(window) = application.main_window() {
+                    if !window.start_task(&task_text) {
+                        window.display_toast(&gettext("Stop the timer to start a new task."));
+                    }
+                }
+            } else if options.contains("stop") {
+                if let Some(window) = application.main_window() {
+                    window.stop_task();
+                }
+            } else if options.contains("toggle") {
+                if let Some(window) = application.main_window() {
+                    window.toggle_timer();
+                }
+            } else {
+                application.activate();
+            }
+            0
+        }
+
         // We connect to the activate callback to create a window when the application
         // has been launched. Additionally, this callback notifies us when the user
         // tries to launch a "second instance" of the application. When they try
         // to do that, we'll just present any existing window.
         fn activate(&self, application: &Self::Type) {
-            // Initialize the database
-            let _ = database::db_init();
-            let _ = database::upgrade_old_db();
-
             // Get the current window or create one if necessary
             let window = if let Some(window) = application.active_window() {
                 window
@@ -97,10 +130,30 @@ impl FurtheranceApplication {
             .expect("Failed to create FurtheranceApplication")
     }
 
+    pub fn main_window(&self) -> Option<FurtheranceWindow> {
+        // The timer window, opening it if the app is running without one
+        self.open_window().or_else(|| {
+            self.activate();
+            self.open_window()
+        })
+    }
+
+    pub fn open_window(&self) -> Option<FurtheranceWindow> {
+        // The timer window, only if it is already open
+        self.windows()
+            .into_iter()
+            .find_map(|window| window.downcast::<FurtheranceWindow>().ok())
+    }
+
     fn setup_gactions(&self) {
         let quit_action = gio::SimpleAction::new("quit", None);
         quit_action.connect_activate(clone!(@weak self as app => move |_, _| {
-            app.quit();
+            // Closing the window asks what to do with a running timer first
+            let running_window = app.open_window().filter(|window| window.timer_status().is_some());
+            match running_window {
+                Some(window) => window.close(),
+                None => app.quit(),
+            }
         }));
         self.add_action(&quit_action);
 
@@ -118,6 +171,50 @@ impl FurtheranceApplication {
         self.set_accels_for_action("app.report", &["<primary>R"]);
         self.add_action(&report_action);
 
+        let start_last_task_action = gio::SimpleAction::new("start-last-task", None);
+        start_last_task_action.connect_activate(clone!(@weak self as app => move |_, _| {
+            if let Some(window) = app.main_window() {
+                if !window.start_last_task() && window.timer_status().is_some() {
+                    window.display_toast(&gettext("Stop the timer to start a new task."));
+                }
+            }
+        }));
+        self.add_action(&start_last_task_action);
+
+        let start_task_action = gio::SimpleAction::new("start-task", Some(glib::VariantTy::STRING));
+        start_task_action.connect_activate(clone!(@weak self as app => move |_, task_text| {
+            let task_text = task_text.and_then(|x| x.get::<String>()).unwrap_or_default();
+            if let Some(window) = app.main_window() {
+                if !window.start_task(&task_text) && window.timer_status().is_some() {
+                    window.display_toast(&gettext("Stop the timer to start a new task."));
+                }
+            }
+        }));
+        self.add_action(&start_task_action);
+
+        let stop_timer_action = gio::SimpleAction::new("stop-timer", None);
+        stop_timer_action.connect_activate(clone!(@weak self as app => move |_, _| {
+            if let Some(window) = app.main_window() {
+                window.stop_task();
+            }
+        }));
+        self.add_action(&stop_timer_action);
+
+        let new_entry_action = gio::SimpleAction::new("new-entry", None);
+        new_entry_action.connect_activate(clone!(@weak self as app => move |_, _| {
+            if let Some(window) = app.main_window() {
+                window.present();
+                window.add_task();
+            }
+        }));
+        self.add_action(&new_entry_action);
+
+        let import_action = gio::SimpleAction::new("import", None);
+        import_action.connect_activate(clone!(@weak self as app => move |_, _| {
+            app.import_history();
+        }));
+        self.add_action(&import_action);
+
         let about_action = gio::SimpleAction::new("about", None);
         about_action.connect_activate(clone!(@weak self as app => move |_, _| {
             app.show_about();
@@ -126,7 +223,11 @@ impl FurtheranceApplication {
 
         let discard_idle_action = gio::SimpleAction::new("discard-idle-action", None);
         discard_idle_action.connect_activate(clone!(@weak self as app => move |_, _| {
-            let window = FurtheranceWindow::default();
+            // The notification can outlive the window
+            let window = match app.open_window() {
+                Some(window) => window,
+                None => return,
+            };
             let imp = window.imp();
             if *imp.running.lock().unwrap() && *imp.idle_time_reached.lock().unwrap() {
                 window.imp().idle_dialog.lock().unwrap().response(gtk::ResponseType::Reject);
@@ -134,9 +235,30 @@ impl FurtheranceApplication {
         }));
         self.add_action(&discard_idle_action);
 
+        // The other answers to the idle dialog, for the notification's buttons
+        for (name, response) in [("discard-idle-time-action", window::IDLE_DISCARD),
+                                 ("split-idle-action", window::IDLE_SPLIT),
+                                 ("save-idle-action", window::IDLE_SAVE_AS_ENTRY)] {
+            let idle_action = gio::SimpleAction::new(name, None);
+            idle_action.connect_activate(clone!(@weak self as app => move |_, _| {
+                let window = match app.open_window() {
+                    Some(window) => window,
+                    None => return,
+                };
+                let imp = window.imp();
+                if *imp.running.lock().unwrap() && *imp.idle_time_reached.lock().unwrap() {
+                    imp.idle_dialog.lock().unwrap().response(response);
+                }
+            }));
+            self.add_action(&idle_action);
+        }
+
         let continue_idle_action = gio::SimpleAction::new("continue-idle-action", None);
         continue_idle_action.connect_activate(clone!(@weak self as app => move |_, _| {
-            let window = FurtheranceWindow::default();
+            let window = match app.open_window() {
+                Some(window) => window,
+                None => return,
+            };
             if *window.imp().running.lock().unwrap() {
                 window.imp().idle_dialog.lock().unwrap().response(gtk::ResponseType::Accept);
             }
@@ -150,6 +272,13 @@ impl FurtheranceApplication {
         }));
         self.add_action(&continue_pomodoro_action);
 
+        let break_pomodoro_action = gio::SimpleAction::new("break-pomodoro-action", None);
+        break_pomodoro_action.connect_activate(clone!(@weak self as app => move |_, _| {
+            let imp = imp::FurtheranceApplication::from_instance(&app);
+            imp.pomodoro_dialog.lock().unwrap().response(gtk::ResponseType::Apply);
+        }));
+        self.add_action(&break_pomodoro_action);
+
         let stop_pomodoro_action = gio::SimpleAction::new("stop-pomodoro-action", None);
         stop_pomodoro_action.connect_activate(clone!(@weak self as app => move |_, _| {
             let imp = imp::FurtheranceApplication::from_instance(&app);
@@ -158,8 +287,46 @@ impl FurtheranceApplication {
         self.add_action(&stop_pomodoro_action);
     }
 
+    fn setup_command_line(&self) {
+        self.add_main_option(
+            "start",
+            glib::Char::from(0),
+            glib::OptionFlags::NONE,
+            glib::OptionArg::String,
+            &gettext("Start timing a task"),
+            Some("\"TASK #TAG\""),
+        );
+        self.add_main_option(
+            "stop",
+            glib::Char::from(0),
+            glib::OptionFlags::NONE,
+            glib::OptionArg::None,
+            &gettext("Stop the timer"),
+            None,
+        );
+        self.add_main_option(
+            "toggle",
+            glib::Char::from(0),
+            glib::OptionFlags::NONE,
+            glib::OptionArg::None,
+            &gettext("Stop the timer, or start the last task again"),
+            None,
+        );
+    }
+
     fn setup_application(&self) {
         self.update_light_dark();
+        // Stay around for a while when started by a search in GNOME Shell
+        self.set_inactivity_timeout(10000);
+        // Logging out keeps the timer running, like closing the window with "Keep Running"
+        self.set_register_session(true);
+        self.connect_query_end(|app| {
+            if let Some(window) = app.open_window() {
+                if window.timer_status().is_some() {
+                    window.keep_running();
+                }
+            }
+        });
     }
 
     fn show_about(&self) {
@@ -224,6 +391,78 @@ impl FurtheranceApplication {
         dialog.show();
     }
 
+    fn import_history(&self) {
+        // Let the user choose an export from another time tracker
+        let window = FurtheranceWindow::default();
+        let dialog = gtk::FileChooserNative::new(
+            Some(&gettext("Import Tasks")),
+            Some(&window),
+            gtk::FileChooserAction::Open,
+            Some(&gettext("Import")),
+            Some(&gettext("Cancel")),
+        );
+        dialog.set_modal(true);
+
+        let supported_filter = gtk::FileFilter::new();
+        supported_filter.set_name(Some(&gettext("All supported files")));
+        supported_filter.add_pattern("*.csv");
+        supported_filter.add_pattern("*.db");
+        supported_filter.add_pattern("frames");
+        dialog.add_filter(&supported_filter);
+
+        let toggl_filter = gtk::FileFilter::new();
+        toggl_filter.set_name(Some(&gettext("Toggl Track CSV")));
+        toggl_filter.add_mime_type("text/csv");
+        toggl_filter.add_pattern("*.csv");
+        dialog.add_filter(&toggl_filter);
+
+        let hamster_filter = gtk::FileFilter::new();
+        hamster_filter.set_name(Some(&gettext("Hamster database")));
+        hamster_filter.add_pattern("*.db");
+        dialog.add_filter(&hamster_filter);
+
+        let watson_filter = gtk::FileFilter::new();
+        watson_filter.set_name(Some(&gettext("Watson frames")));
+        watson_filter.add_pattern("frames");
+        dialog.add_filter(&watson_filter);
+
+        dialog.add_choice("project-as-tag", &gettext("Add project as a tag"), &[]);
+        dialog.set_choice("project-as-tag", "false");
+        dialog.add_choice("resume-running", &gettext("Continue a running Watson timer"), &[]);
+        dialog.set_choice("resume-running", "true");
+
+        dialog.connect_response(clone!(@strong dialog => move |_, resp| {
+            if resp == gtk::ResponseType::Accept {
+                if let Some(path) = dialog.file().and_then(|file| file.path()) {
+                    let options = import::ImportOptions {
+                        project_as_tag: dialog.choice("project-as-tag")
+                            .map_or(false, |choice| choice == "true"),
+                        resume_running: dialog.choice("resume-running")
+                            .map_or(false, |choice| choice == "true"),
+                    };
+                    match import::import_file(&path, &options) {
+                        Ok(summary) => {
+                            window.reset_history_box();
+                            window.display_toast(&format!("{} {}, {} {}",
+                                gettext("Tasks imported:"), summary.imported,
+                                gettext("duplicates skipped:"), summary.duplicates));
+                            if let Some((task_text, start_time)) = summary.running {
+                                window.resume_task(&task_text, start_time);
+                            }
+                        }
+                        Err(err) => {
+                            debug!("Unable to import tasks: {:?}", err);
+                            window.display_toast(&format!("{} {}", gettext("Import failed."), err));
+                        }
+                    }
+                }
+            }
+            dialog.destroy();
+        }));
+
+        dialog.show();
+    }
+
     pub fn delete_enabled(&self, enabled: bool) {
         if enabled {
             let delete_history_action = gio::SimpleAction::new("delete-history", None);
@@ -249,7 +488,7 @@ impl FurtheranceApplication {
         }
     }
 
-    pub fn system_idle_notification(&self, title: &str, subtitle: &str) {
+    pub fn system_idle_notification(&self, title: &str, subtitle: &str, can_split: bool) {
         let icon = Some("appointment-missed-symbolic");
         let notification = gio::Notification::new(title.as_ref());
         notification.set_body(Some(subtitle.as_ref()));
@@ -261,7 +500,12 @@ impl FurtheranceApplication {
             }
         }
 
-        notification.add_button(&gettext("Discard"), "app.discard-idle-action");
+        notification.add_button(&gettext("Stop"), "app.discard-idle-action");
+        notification.add_button(&gettext("Discard Idle Time"), "app.discard-idle-time-action");
+        if can_split {
+            notification.add_button(&gettext("Split"), "app.split-idle-action");
+            notification.add_button(&gettext("Save as Break"), "app.save-idle-action");
+        }
         notification.add_button(&gettext("Continue"), "app.continue-idle-action");
 
         notification.set_priority(gio::NotificationPriority::High);
@@ -284,6 +528,7 @@ impl FurtheranceApplication {
         }
 
         notification.add_button(&gettext("Continue"), "app.continue-pomodoro-action");
+        notification.add_button(&gettext("Take a Break"), "app.break-pomodoro-action");
         notification.add_button(&gettext("Stop"), "app.stop-pomodoro-action");
 
         notification.set_priority(gio::NotificationPriority::High);
@@ -291,6 +536,32 @@ impl FurtheranceApplication {
         self.withdraw_notification("idle");
         self.send_notification(Some("pomodoro"), &notification);
     }
+
+    pub fn system_break_notification(&self, long: bool) {
+        let body = if long {
+            gettext("Your pomodoro ended, take a long break.")
+        } else {
+            gettext("Your pomodoro ended, take a short break.")
+        };
+        self.pomodoro_cycle_notification(&gettext("Time for a break"), &body);
+    }
+
+    pub fn system_break_over_notification(&self) {
+        self.pomodoro_cycle_notification(&gettext("Break is over"), &gettext("Time to get back to work."));
+    }
+
+    fn pomodoro_cycle_notification(&self, title: &str, body: &str) {
+        let notification = gio::Notification::new(title);
+        notification.set_body(Some(body));
+        match gio::Icon::for_string("alarm-symbolic") {
+            Ok(gicon) => notification.set_icon(&gicon),
+            Err(err) => debug!("Unable to display notification: {:?}", err),
+        }
+        notification.set_priority(gio::NotificationPriority::High);
+
+        self.withdraw_notification("idle");
+        self.send_notification(Some("pomodoro"), &notification);
+    }
 }
 
 impl Default for FurtheranceApplication {
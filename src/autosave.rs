@@ -0,0 +1,213 @@
+// Furtherance - Track your time without being tracked
+// Copyright (C) 2022  Ricky Kresslein <rk@lakoliu.com>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+use chrono::{DateTime, Duration, Local};
+use directories::ProjectDirs;
+use serde_json::{json, Value};
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::path::PathBuf;
+
+use crate::database::Task;
+use crate::quick_entry::{self, QuickEntry};
+use crate::timer::TimerState;
+use crate::utils;
+
+// Bump this when the format changes, older versions are still read
+const VERSION: u64 = 1;
+const FILE_NAME: &str = "furtherance_autosave.json";
+// Four lines: task name, start time, autosave time and tags
+const OLD_FILE_NAME: &str = "furtherance_autosave.txt";
+
+// Everything needed to save or continue a task that was running when the app quit
+#[derive(Debug, Clone, PartialEq)]
+pub struct Autosave {
+    pub task_text: String,
+    pub timer: TimerState,
+    // Pomodoros finished during this entry, and in the cycle so far
+    pub pomodoros: i32,
+    pub pomodoro_count: i32,
+    pub saved_at: DateTime<Local>,
+}
+
+impl Autosave {
+    pub fn start_time(&self) -> DateTime<Local> {
+        self.timer.start_time().unwrap_or(self.saved_at)
+    }
+
+    pub fn stop_time(&self) -> DateTime<Local> {
+        // A paused task ended when it was paused
+        match self.timer {
+            TimerState::Paused { paused_at, .. } => paused_at,
+            _ => self.saved_at,
+        }
+    }
+
+    pub fn to_task(&self) -> Task {
+        // The entry as it was at the last autosave
+        let entry = quick_entry::parse(&self.task_text).unwrap_or_else(|_| {
+            let (task_name, tag_list) = utils::split_tags_and_task(&self.task_text);
+            QuickEntry {
+                task_name,
+                tags: quick_entry::split_tags(&tag_list),
+                ..QuickEntry::default()
+            }
+        });
+        let mut task = entry.to_task(self.start_time(), self.stop_time());
+        task.pomodoros = self.pomodoros;
+        task
+    }
+}
+
+fn get_directory() -> Option<PathBuf> {
+    let proj_dirs = ProjectDirs::from("com", "lakoliu", "Furtherance")?;
+    let path = PathBuf::from(proj_dirs.data_dir());
+    fs::create_dir_all(&path).ok()?;
+    Some(path)
+}
+
+// is_some_and is newer than the Rust in the Flatpak SDK
+#[allow(clippy::unnecessary_map_or)]
+pub fn exists() -> bool {
+    get_directory().map_or(false, |dir| dir.join(FILE_NAME).exists() || dir.join(OLD_FILE_NAME).exists())
+}
+
+pub fn write(autosave: &Autosave) -> io::Result<()> {
+    // Write a new file and move it over the old one, so there is never half an autosave
+    let dir = get_directory().ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "No data directory"))?;
+    let temp_path = dir.join(format!("{}.tmp", FILE_NAME));
+    let mut file = File::create(&temp_path)?;
+    file.write_all(serialize(autosave).as_bytes())?;
+    file.sync_all()?;
+    fs::rename(&temp_path, dir.join(FILE_NAME))
+}
+
+pub fn read() -> Option<Autosave> {
+    let dir = get_directory()?;
+    match fs::read_to_string(dir.join(FILE_NAME)) {
+        Ok(contents) => deserialize(&contents),
+        Err(_) => read_old(&fs::read_to_string(dir.join(OLD_FILE_NAME)).ok()?),
+    }
+}
+
+pub fn delete() {
+    if let Some(dir) = get_directory() {
+        let _ = fs::remove_file(dir.join(FILE_NAME));
+        let _ = fs::remove_file(dir.join(OLD_FILE_NAME));
+    }
+}
+
+pub fn serialize(autosave: &Autosave) -> String {
+    // Also kept with the running task in the database
+    to_json(autosave).to_string()
+}
+
+pub fn deserialize(contents: &str) -> Option<Autosave> {
+    from_json(&serde_json::from_str(contents).ok()?)
+}
+
+fn to_json(autosave: &Autosave) -> Value {
+    // The paused time is only written for older versions, the pauses are read back
+    let paused = autosave.timer.paused().num_seconds();
+    let timer = match autosave.timer {
+        TimerState::Running { start_time, .. } => json!({
+            "state": "running",
+            "start_time": start_time.to_rfc3339(),
+            "paused": paused,
+        }),
+        TimerState::Paused { start_time, paused_at, .. } => json!({
+            "state": "paused",
+            "start_time": start_time.to_rfc3339(),
+            "paused": paused,
+            "paused_at": paused_at.to_rfc3339(),
+        }),
+        TimerState::Pomodoro { start_time, length, .. } => json!({
+            "state": "pomodoro",
+            "start_time": start_time.to_rfc3339(),
+            "length": length.num_seconds(),
+        }),
+        // Breaks and a stopped timer have no task to save
+        TimerState::Idle | TimerState::Break { .. } => Value::Null,
+    };
+    let pauses: Vec<Value> = autosave.timer.pauses().iter()
+        .map(|(start, stop)| json!([start.to_rfc3339(), stop.to_rfc3339()]))
+        .collect();
+    json!({
+        "version": VERSION,
+        "task": autosave.task_text,
+        "timer": timer,
+        "pauses": pauses,
+        "pomodoros": autosave.pomodoros,
+        "pomodoro_count": autosave.pomodoro_count,
+        "saved_at": autosave.saved_at.to_rfc3339(),
+    })
+}
+
+fn from_json(json: &Value) -> Option<Autosave> {
+    // Anything missing or from a newer version makes the whole autosave unreadable
+    if json["version"].as_u64()? > VERSION {
+        return None;
+    }
+    let pauses = json["pauses"].as_array()?
+        .iter()
+        .map(|pause| Some((time(&pause[0])?, time(&pause[1])?)))
+        .collect::<Option<Vec<_>>>()?;
+    let timer = &json["timer"];
+    let start_time = time(&timer["start_time"])?;
+    let timer = match timer["state"].as_str()? {
+        "running" => TimerState::Running { start_time, pauses },
+        "paused" => TimerState::Paused {
+            start_time,
+            pauses,
+            paused_at: time(&timer["paused_at"])?,
+        },
+        "pomodoro" => TimerState::Pomodoro {
+            start_time,
+            length: Duration::seconds(timer["length"].as_i64()?),
+            pauses,
+        },
+        _ => return None,
+    };
+
+    Some(Autosave {
+        task_text: json["task"].as_str()?.to_string(),
+        timer,
+        pomodoros: json["pomodoros"].as_i64()? as i32,
+        pomodoro_count: json["pomodoro_count"].as_i64()? as i32,
+        saved_at: time(&json["saved_at"])?,
+    })
+}
+
+fn read_old(contents: &str) -> Option<Autosave> {
+    // The autosave from before it was JSON
+    let lines: Vec<&str> = contents.lines().collect();
+    let (task_name, start_time, saved_at) = match lines[..] {
+        [task_name, start_time, saved_at, ..] => (task_name, start_time, saved_at),
+        _ => return None,
+    };
+    let tags = lines.get(3).copied().unwrap_or_default();
+    Some(Autosave {
+        task_text: utils::task_text(task_name, tags),
+        timer: TimerState::running(DateTime::parse_from_rfc3339(start_time).ok()?.with_timezone(&Local)),
+        pomodoros: 0,
+        pomodoro_count: 0,
+        saved_at: DateTime::parse_from_rfc3339(saved_at).ok()?.with_timezone(&Local),
+    })
+}
+
+fn time(value: &Value) -> Option<DateTime<Local>> {
+    let time = DateTime::parse_from_rfc3339(value.as_str()?).ok()?;
+    Some(time.with_timezone(&Local))
+}
@@ -0,0 +1,339 @@
+// Furtherance - Track your time without being tracked
+// Copyright (C) 2022  Ricky Kresslein <rk@lakoliu.com>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use chrono::{DateTime, Local, NaiveDate, NaiveDateTime, NaiveTime, offset::TimeZone};
+use gettextrs::*;
+use serde_json::json;
+use std::error::Error;
+use std::io::{self, Write};
+use std::thread;
+use std::time::Duration;
+
+use crate::autosave::{self, Autosave};
+use crate::config;
+use crate::database;
+use crate::quick_entry;
+use crate::utils;
+
+const USAGE: &str = "Usage:
+  furtherance start TASK [#TAG...] [@PROJECT] [$] [-15m | ~1h30]
+  furtherance stop
+  furtherance status [--format json|text] [--follow]
+  furtherance add --start TIME --stop TIME TASK [#TAG...]
+  furtherance list [--since YYYY-MM-DD]
+
+TIME is YYYY-MM-DD HH:MM[:SS], or HH:MM for today.";
+
+pub fn run(args: &[String]) -> Option<i32> {
+    // Run a command without opening a window, or return None if there is none
+    let command = args.get(1)?;
+    let options = &args[2..];
+    let result = match command.as_str() {
+        "start" => start(options),
+        "stop" => stop(),
+        "status" => status(options),
+        "add" => add(options),
+        "list" => list(options),
+        "help" => {
+            println!("{}", USAGE);
+            Ok(0)
+        }
+        _ => return None,
+    };
+
+    match result {
+        Ok(code) => Some(code),
+        Err(err) => {
+            eprintln!("furtherance: {}", err);
+            Some(1)
+        }
+    }
+}
+
+fn start(options: &[String]) -> Result<i32, Box<dyn Error>> {
+    database::init();
+    let entry = quick_entry::parse(&options.join(" "))?;
+    let tag_list = entry.tag_list();
+    if let Some(length) = entry.logged {
+        // "~1h30" logs a finished entry instead
+        let stop_time = Local::now();
+        database::write_entry(&entry.to_task(stop_time - length, stop_time), &[])?;
+        println!("{} {} ({})", gettext("Added"), entry.task_text(), quick_entry::format_length(length));
+        return Ok(0);
+    }
+
+    if let Some(running) = database::get_running()? {
+        let message = format!("{} {}",
+                              utils::task_text(&running.task_name, &running.tags),
+                              gettext("is already running."));
+        return Err(message.into());
+    }
+
+    let start_time = Local::now() - entry.start_offset.unwrap_or_else(chrono::Duration::zero);
+    database::start_running(&entry.task_name,
+                            start_time,
+                            &tag_list,
+                            entry.project.as_deref().unwrap_or_default(),
+                            entry.billable)?;
+    println!("{} {}", gettext("Started"), utils::task_text(&entry.task_name, &tag_list));
+    Ok(0)
+}
+
+fn stop() -> Result<i32, Box<dyn Error>> {
+    database::init();
+    // A task the window left running keeps its pauses and pomodoros
+    if let Some(saved) = database::get_running()?.and_then(|running| autosave::deserialize(&running.state)) {
+        let saved = Autosave { saved_at: Local::now(), ..saved };
+        if database::finish_running(&saved.to_task(), saved.timer.pauses())? {
+            println!("{} {} ({})",
+                     gettext("Stopped"),
+                     saved.task_text,
+                     utils::format_duration(saved.timer.elapsed(saved.saved_at).num_seconds(), true));
+            return Ok(0);
+        }
+    }
+
+    let stop_time = Local::now();
+    match database::stop_running(stop_time)? {
+        Some(running) => {
+            let start_time = DateTime::parse_from_rfc3339(&running.start_time)?.with_timezone(&Local);
+            println!("{} {} ({})",
+                     gettext("Stopped"),
+                     utils::task_text(&running.task_name, &running.tags),
+                     utils::format_duration((stop_time - start_time).num_seconds(), true));
+            Ok(0)
+        }
+        None => Err(gettext("No task is running.").into()),
+    }
+}
+
+// The running task as the app or the database sees it
+struct Status {
+    task_name: String,
+    tags: String,
+    elapsed: i64,
+    pomodoro_remaining: Option<i64>,
+}
+
+fn status(options: &[String]) -> Result<i32, Box<dyn Error>> {
+    let mut format: Option<&str> = None;
+    let mut follow = false;
+    let mut options = options.iter();
+    while let Some(option) = options.next() {
+        match option.as_str() {
+            "--format" => match options.next().map(|x| x.as_str()) {
+                Some(name) if name == "json" || name == "text" => format = Some(name),
+                _ => return Err(USAGE.into()),
+            },
+            "--follow" => follow = true,
+            _ => return Err(USAGE.into()),
+        }
+    }
+
+    database::init();
+    let stdout = io::stdout();
+    loop {
+        let status = current_status()?;
+        let line = match (format, &status) {
+            (Some("json"), _) => status_json(&status),
+            (_, Some(status)) => status_text(status),
+            // Panels hide modules that print nothing
+            (Some(_), None) => String::new(),
+            (None, None) => gettext("No task is running."),
+        };
+
+        let mut stdout = stdout.lock();
+        if writeln!(stdout, "{}", line).and_then(|_| stdout.flush()).is_err() {
+            // The panel went away
+            return Ok(0);
+        }
+        if !follow {
+            // Exit with 1 so scripts can check if a task is running
+            return Ok(if status.is_some() { 0 } else { 1 });
+        }
+        thread::sleep(Duration::from_secs(1));
+    }
+}
+
+fn current_status() -> Result<Option<Status>, Box<dyn Error>> {
+    // Ask the open app first, it also knows about timers that aren't saved yet
+    // The app and the saved timer have the task the way it was typed in
+    let ((task_name, tags), elapsed) = match instance_status() {
+        Some(Some((task_text, elapsed))) => (quick_entry::split_task_text(&task_text), elapsed),
+        Some(None) => return Ok(None),
+        None => match database::get_running()? {
+            Some(running) => match autosave::deserialize(&running.state) {
+                Some(saved) => (quick_entry::split_task_text(&saved.task_text),
+                                saved.timer.elapsed(Local::now()).num_seconds()),
+                None => {
+                    let start_time = DateTime::parse_from_rfc3339(&running.start_time)?.with_timezone(&Local);
+                    ((running.task_name, running.tags), (Local::now() - start_time).num_seconds())
+                }
+            },
+            None => return Ok(None),
+        },
+    };
+
+    Ok(Some(Status {
+        task_name,
+        tags,
+        elapsed,
+        pomodoro_remaining: pomodoro_remaining(),
+    }))
+}
+
+fn instance_status() -> Option<Option<(String, i64)>> {
+    // None if the app isn't running, otherwise its task and elapsed seconds
+    let conn = dbus::blocking::Connection::new_session().ok()?;
+    // Calling the app directly would start it through D-Bus activation
+    let bus = conn.with_proxy("org.freedesktop.DBus", "/org/freedesktop/DBus", Duration::from_millis(1000));
+    let (has_owner,): (bool,) = bus.method_call("org.freedesktop.DBus", "NameHasOwner", (config::APP_ID,)).ok()?;
+    if !has_owner {
+        return None;
+    }
+    let object_path = format!("/{}", config::APP_ID.replace('.', "/"));
+    let proxy = conn.with_proxy(config::APP_ID, object_path, Duration::from_millis(1000));
+    let (running, task_text, _, elapsed): (bool, String, i64, i64) =
+        proxy.method_call("com.lakoliu.Furtherance", "GetStatus", ()).ok()?;
+    if running {
+        Some(Some((task_text, elapsed)))
+    } else {
+        Some(None)
+    }
+}
+
+fn pomodoro_remaining() -> Option<i64> {
+    // Only the window runs pomodoros, and it keeps its timer with the running task
+    let running = database::get_running().ok()??;
+    let saved = autosave::deserialize(&running.state)?;
+    saved.timer.remaining(Local::now()).map(|remaining| remaining.num_seconds())
+}
+
+fn status_text(status: &Status) -> String {
+    let time = match status.pomodoro_remaining {
+        Some(remaining) => utils::format_duration(remaining, true),
+        None => utils::format_duration(status.elapsed, true),
+    };
+    format!("{}  {}", utils::task_text(&status.task_name, &status.tags), time)
+}
+
+fn status_json(status: &Option<Status>) -> String {
+    // Waybar's custom module format, with the raw values for other tools
+    let status = match status {
+        Some(status) => status,
+        None => {
+            return json!({
+                "text": "",
+                "alt": "stopped",
+                "class": "stopped",
+                "tooltip": gettext("No task is running."),
+            }).to_string()
+        }
+    };
+
+    let tags = quick_entry::split_tags(&status.tags);
+    let mut output = json!({
+        "text": status_text(status),
+        "alt": "running",
+        "class": "running",
+        "tooltip": format!("{}\n{}",
+                           utils::task_text(&status.task_name, &status.tags),
+                           utils::format_duration(status.elapsed, true)),
+        "task": status.task_name,
+        "tags": tags,
+        "elapsed": status.elapsed,
+        "pomodoro_remaining": status.pomodoro_remaining,
+    });
+    if let Some(remaining) = status.pomodoro_remaining {
+        let total = remaining + status.elapsed;
+        if total > 0 {
+            output["percentage"] = json!(status.elapsed * 100 / total);
+        }
+    }
+    output.to_string()
+}
+
+fn add(options: &[String]) -> Result<i32, Box<dyn Error>> {
+    let mut start_time: Option<DateTime<Local>> = None;
+    let mut stop_time: Option<DateTime<Local>> = None;
+    let mut words: Vec<&str> = Vec::new();
+
+    let mut options = options.iter();
+    while let Some(option) = options.next() {
+        match option.as_str() {
+            "--start" => start_time = Some(parse_time(options.next())?),
+            "--stop" => stop_time = Some(parse_time(options.next())?),
+            word => words.push(word),
+        }
+    }
+
+    let start_time = start_time.ok_or_else(|| gettext("Missing --start time."))?;
+    let stop_time = stop_time.ok_or_else(|| gettext("Missing --stop time."))?;
+    if stop_time < start_time {
+        return Err(gettext("Start time should be before stop time.").into());
+    }
+    let (task_name, tag_list) = utils::split_tags_and_task(&words.join(" "));
+    if task_name.is_empty() {
+        return Err(gettext("Task name cannot be empty.").into());
+    }
+
+    database::init();
+    database::db_write(&task_name, start_time, stop_time, tag_list.clone())?;
+    println!("{} {} ({})",
+             gettext("Added"),
+             utils::task_text(&task_name, &tag_list),
+             utils::format_duration((stop_time - start_time).num_seconds(), true));
+    Ok(0)
+}
+
+fn list(options: &[String]) -> Result<i32, Box<dyn Error>> {
+    let since = match options {
+        [] => Local::today().naive_local(),
+        [option, date] if option == "--since" => NaiveDate::parse_from_str(date, "%Y-%m-%d")?,
+        _ => return Err(USAGE.into()),
+    };
+
+    database::init();
+    let mut tasks = database::retrieve()?;
+    tasks.sort_by(|a, b| a.start_time.cmp(&b.start_time));
+    for task in tasks {
+        let start = DateTime::parse_from_rfc3339(&task.start_time)?.with_timezone(&Local);
+        let stop = DateTime::parse_from_rfc3339(&task.stop_time)?.with_timezone(&Local);
+        if start.date().naive_local() < since {
+            continue;
+        }
+        println!("{} {}–{}  {}  {}",
+                 start.format("%Y-%m-%d"),
+                 start.format("%H:%M"),
+                 stop.format("%H:%M"),
+                 utils::format_duration((stop - start).num_seconds() - task.paused, true),
+                 utils::task_text(&task.task_name, &task.tags));
+    }
+    Ok(0)
+}
+
+fn parse_time(time: Option<&String>) -> Result<DateTime<Local>, Box<dyn Error>> {
+    let time = time.ok_or(USAGE)?;
+    let naive = NaiveDateTime::parse_from_str(time, "%Y-%m-%d %H:%M:%S")
+        .or_else(|_| NaiveDateTime::parse_from_str(time, "%Y-%m-%d %H:%M"))
+        .or_else(|_| {
+            NaiveTime::parse_from_str(time, "%H:%M").map(|t| Local::today().naive_local().and_time(t))
+        })?;
+    let local = Local.from_local_datetime(&naive)
+        .earliest()
+        .ok_or("Time does not exist in the local time zone")?;
+    Ok(local)
+}
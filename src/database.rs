@@ -14,11 +14,67 @@
 // You should have received a copy of the GNU General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
-use rusqlite::{Connection, Result};
+use rusqlite::{Connection, OptionalExtension, Result};
 use chrono::{DateTime, Local};
 use directories::ProjectDirs;
+use once_cell::sync::Lazy;
+use std::collections::HashMap;
+use std::ops::{Deref, DerefMut};
 use std::path::PathBuf;
-use std::fs::create_dir_all;
+use std::fs::{self, create_dir_all};
+use std::sync::Mutex;
+use std::time::SystemTime;
+
+// Pauses of the running task are kept under this id until it is saved
+const RUNNING_TASK_ID: i64 = 0;
+
+// When the database file was last changed by this process
+static OWN_WRITE: Lazy<Mutex<Option<SystemTime>>> = Lazy::new(|| Mutex::new(None));
+
+// A connection that remembers if it changed the database once it is closed
+struct Db(Option<Connection>);
+
+impl Deref for Db {
+    type Target = Connection;
+
+    fn deref(&self) -> &Connection {
+        self.0.as_ref().unwrap()
+    }
+}
+
+impl DerefMut for Db {
+    fn deref_mut(&mut self) -> &mut Connection {
+        self.0.as_mut().unwrap()
+    }
+}
+
+impl Drop for Db {
+    fn drop(&mut self) {
+        let conn = match self.0.take() {
+            Some(conn) => conn,
+            None => return,
+        };
+        let changes = conn.query_row("SELECT total_changes()", [], |row| row.get::<_, i64>(0));
+        // The file is only written for good once the connection closes
+        drop(conn);
+        if changes.unwrap_or(0) > 0 {
+            *OWN_WRITE.lock().unwrap() = modified();
+        }
+    }
+}
+
+fn open() -> Result<Db> {
+    Ok(Db(Some(Connection::open(get_directory())?)))
+}
+
+pub fn modified() -> Option<SystemTime> {
+    fs::metadata(get_directory()).and_then(|metadata| metadata.modified()).ok()
+}
+
+pub fn changed_elsewhere() -> bool {
+    // Whether the last change to the file came from another process, like the command line
+    modified() != *OWN_WRITE.lock().unwrap()
+}
 
 #[derive(Clone, Debug)]
 pub struct Task {
@@ -27,6 +83,35 @@ pub struct Task {
     pub start_time: String,
     pub stop_time: String,
     pub tags: String,
+    pub description: String,
+    pub pomodoros: i32,
+    pub project: String,
+    pub billable: bool,
+    // Seconds the task was paused, which don't count towards its time
+    pub paused: i64,
+}
+
+// A break taken without stopping the task
+#[derive(Clone, Debug)]
+pub struct Pause {
+    pub task_id: i32,
+    pub start_time: String,
+    pub stop_time: String,
+}
+
+// The task that is currently being timed, by the window or the command line
+#[derive(Clone, Debug)]
+pub struct RunningTask {
+    pub task_name: String,
+    pub start_time: String,
+    pub tags: String,
+    pub project: String,
+    pub billable: bool,
+    pub pomodoros: i32,
+    // Closed with "Keep Running", so the window continues it without asking
+    pub keep_running: bool,
+    // The window's timer, pauses and pomodoros, empty for tasks started from the command line
+    pub state: String,
 }
 
 pub fn get_directory() -> PathBuf {
@@ -39,8 +124,22 @@ pub fn get_directory() -> PathBuf {
     PathBuf::new()
 }
 
+pub fn init() {
+    // Create the database or bring an old one up to date
+    let _ = db_init();
+    let _ = upgrade_old_db();
+    let _ = add_description();
+    let _ = add_pomodoros();
+    let _ = add_project();
+    let _ = add_running_table();
+    let _ = add_running_state();
+    let _ = add_running_project();
+    let _ = add_running_keep();
+    let _ = add_pauses_table();
+}
+
 pub fn db_init() -> Result<()> {
-    let conn = Connection::open(get_directory())?;
+    let conn = open()?;
     conn.execute(
         "CREATE TABLE tasks (
                     id integer primary key,
@@ -56,7 +155,7 @@ pub fn db_init() -> Result<()> {
 
 pub fn upgrade_old_db() -> Result<()> {
     // Update from old DB w/o tags
-    let conn = Connection::open(get_directory())?;
+    let conn = open()?;
 
     conn.execute(
         "ALTER TABLE tasks ADD COLUMN tags TEXT DEFAULT ' '",
@@ -66,12 +165,255 @@ pub fn upgrade_old_db() -> Result<()> {
     Ok(())
 }
 
+pub fn add_description() -> Result<()> {
+    // Update from old DB w/o descriptions
+    let conn = open()?;
+
+    conn.execute(
+        "ALTER TABLE tasks ADD COLUMN description TEXT DEFAULT ''",
+        [],
+    )?;
+
+    Ok(())
+}
+
+pub fn add_pomodoros() -> Result<()> {
+    // Update from old DB w/o pomodoro counts
+    let conn = open()?;
+
+    conn.execute(
+        "ALTER TABLE tasks ADD COLUMN pomodoros INTEGER DEFAULT 0",
+        [],
+    )?;
+
+    Ok(())
+}
+
+pub fn add_project() -> Result<()> {
+    // Update from old DB w/o projects or billable entries
+    let conn = open()?;
+
+    conn.execute(
+        "ALTER TABLE tasks ADD COLUMN project TEXT DEFAULT ''",
+        [],
+    )?;
+    conn.execute(
+        "ALTER TABLE tasks ADD COLUMN billable INTEGER DEFAULT 0",
+        [],
+    )?;
+
+    Ok(())
+}
+
+pub fn add_running_table() -> Result<()> {
+    // Holds at most one row, the task that is being timed right now
+    let conn = open()?;
+
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS running (
+                    id integer primary key check (id = 1),
+                    task_name text,
+                    start_time timestamp,
+                    tags text)",
+        [],
+    )?;
+
+    Ok(())
+}
+
+pub fn add_running_state() -> Result<()> {
+    // Update from old DB w/o the running timer's state
+    let conn = open()?;
+
+    conn.execute(
+        "ALTER TABLE running ADD COLUMN state TEXT DEFAULT ''",
+        [],
+    )?;
+
+    Ok(())
+}
+
+pub fn add_running_project() -> Result<()> {
+    // Update from old DB w/o the running task's project or billable flag
+    let conn = open()?;
+
+    conn.execute(
+        "ALTER TABLE running ADD COLUMN project TEXT DEFAULT ''",
+        [],
+    )?;
+    conn.execute(
+        "ALTER TABLE running ADD COLUMN billable INTEGER DEFAULT 0",
+        [],
+    )?;
+
+    Ok(())
+}
+
+pub fn add_running_keep() -> Result<()> {
+    // Update from old DB w/o the running task's pomodoros or the choice to keep it running
+    let conn = open()?;
+
+    conn.execute(
+        "ALTER TABLE running ADD COLUMN pomodoros INTEGER DEFAULT 0",
+        [],
+    )?;
+    conn.execute(
+        "ALTER TABLE running ADD COLUMN keep_running INTEGER DEFAULT 0",
+        [],
+    )?;
+
+    Ok(())
+}
+
+pub fn add_pauses_table() -> Result<()> {
+    // Breaks within a task, so it stays one entry
+    let conn = open()?;
+
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS pauses (
+                    id integer primary key,
+                    task_id integer,
+                    start_time timestamp,
+                    stop_time timestamp)",
+        [],
+    )?;
+
+    Ok(())
+}
+
+pub fn start_running(task_name: &str,
+                     start_time: DateTime<Local>,
+                     tags: &str,
+                     project: &str,
+                     billable: bool) -> Result<()> {
+    let mut conn = open()?;
+    let tx = conn.transaction()?;
+
+    tx.execute(
+        "INSERT OR REPLACE INTO running (id, task_name, start_time, tags, project, billable)
+            values (1, ?1, ?2, ?3, ?4, ?5)",
+        [&task_name.to_string(), &start_time.to_rfc3339(), &tags.to_string(),
+          &project.to_string(), &(billable as i32).to_string()],
+    )?;
+    tx.execute("DELETE FROM pauses WHERE task_id = ?1", [RUNNING_TASK_ID])?;
+    tx.commit()?;
+
+    Ok(())
+}
+
+pub fn update_running_state(start_time: &str,
+                            state: &str,
+                            pomodoros: i32,
+                            pauses: &[(DateTime<Local>, DateTime<Local>)]) -> Result<()> {
+    // Only if it is still the same task, the command line may have replaced it
+    let mut conn = open()?;
+    let tx = conn.transaction()?;
+
+    let updated = tx.execute(
+        "UPDATE running SET state = (?1), pomodoros = (?2) WHERE id = 1 AND start_time = (?3)",
+        [state, &pomodoros.to_string(), start_time],
+    )?;
+    if updated == 0 {
+        return Ok(());
+    }
+    tx.execute("DELETE FROM pauses WHERE task_id = ?1", [RUNNING_TASK_ID])?;
+    for (pause_start, pause_stop) in pauses {
+        tx.execute(
+            "INSERT INTO pauses (task_id, start_time, stop_time) values (?1, ?2, ?3)",
+            [&RUNNING_TASK_ID.to_string(), &pause_start.to_rfc3339(), &pause_stop.to_rfc3339()],
+        )?;
+    }
+    tx.commit()?;
+
+    Ok(())
+}
+
+pub fn keep_running(start_time: &str) -> Result<()> {
+    // The window closed without stopping the task
+    let conn = open()?;
+
+    conn.execute(
+        "UPDATE running SET keep_running = 1 WHERE id = 1 AND start_time = (?1)",
+        [start_time],
+    )?;
+
+    Ok(())
+}
+
+pub fn get_running() -> Result<Option<RunningTask>> {
+    let conn = open()?;
+
+    conn.query_row(
+        "SELECT task_name, start_time, tags, state, project, billable, pomodoros, keep_running
+            FROM running WHERE id = 1",
+        [],
+        running_from_row,
+    ).optional()
+}
+
+fn running_from_row(row: &rusqlite::Row) -> Result<RunningTask> {
+    Ok(RunningTask {
+        task_name: row.get(0)?,
+        start_time: row.get(1)?,
+        tags: row.get(2)?,
+        state: row.get::<_, Option<String>>(3)?.unwrap_or_default(),
+        project: row.get::<_, Option<String>>(4)?.unwrap_or_default(),
+        billable: row.get::<_, Option<bool>>(5)?.unwrap_or_default(),
+        pomodoros: row.get::<_, Option<i32>>(6)?.unwrap_or_default(),
+        keep_running: row.get::<_, Option<bool>>(7)?.unwrap_or_default(),
+    })
+}
+
+pub fn clear_running() -> Result<usize> {
+    // Returns how many rows were removed, so 0 if someone else stopped the task
+    let mut conn = open()?;
+    let tx = conn.transaction()?;
+
+    let removed = tx.execute("DELETE FROM running", [])?;
+    tx.execute("DELETE FROM pauses WHERE task_id = ?1", [RUNNING_TASK_ID])?;
+    tx.commit()?;
+
+    Ok(removed)
+}
+
+pub fn stop_running(stop_time: DateTime<Local>) -> Result<Option<RunningTask>> {
+    // Save the running task and clear it in one go
+    let mut conn = open()?;
+    let tx = conn.transaction()?;
+
+    let running = tx.query_row(
+        "SELECT task_name, start_time, tags, state, project, billable, pomodoros, keep_running
+            FROM running WHERE id = 1",
+        [],
+        running_from_row,
+    ).optional()?;
+
+    if let Some(running) = &running {
+        tx.execute(
+            "INSERT INTO tasks (task_name, start_time, stop_time, tags, pomodoros, project, billable)
+                values (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            &[&running.task_name, &running.start_time, &stop_time.to_rfc3339(), &running.tags,
+              &running.pomodoros.to_string(), &running.project, &(running.billable as i32).to_string()],
+        )?;
+        // Its pauses now belong to the saved task
+        tx.execute(
+            "UPDATE pauses SET task_id = ?1 WHERE task_id = ?2",
+            [tx.last_insert_rowid(), RUNNING_TASK_ID],
+        )?;
+        tx.execute("DELETE FROM running", [])?;
+    }
+
+    tx.commit()?;
+
+    Ok(running)
+}
+
 pub fn db_write(task_name: &str,
                 start_time: DateTime<Local>,
                 stop_time: DateTime<Local>,
                 tags: String) -> Result<()> {
     // Write data into database
-    let conn = Connection::open(get_directory())?;
+    let conn = open()?;
 
     conn.execute(
         "INSERT INTO tasks (task_name, start_time, stop_time, tags) values (?1, ?2, ?3, ?4)",
@@ -81,24 +423,91 @@ pub fn db_write(task_name: &str,
     Ok(())
 }
 
-pub fn write_autosave(task_name: &str,
-                start_time: &str,
-                stop_time: &str,
-                tags: &str) -> Result<()> {
-    // Write data into database
-    let conn = Connection::open(get_directory())?;
+pub fn write_entry(task: &Task, pauses: &[(DateTime<Local>, DateTime<Local>)]) -> Result<()> {
+    // Write a timed task with its pauses and finished pomodoros
+    let mut conn = open()?;
+    let tx = conn.transaction()?;
+    insert_entry(&tx, task, pauses)?;
+    tx.commit()?;
 
-    conn.execute(
-        "INSERT INTO tasks (task_name, start_time, stop_time, tags) values (?1, ?2, ?3, ?4)",
-        &[&task_name, &start_time, &stop_time, &tags],
+    Ok(())
+}
+
+pub fn finish_running(task: &Task, pauses: &[(DateTime<Local>, DateTime<Local>)]) -> Result<bool> {
+    // Save the running task as it was tracked by the window, false if it was already stopped
+    let mut conn = open()?;
+    let tx = conn.transaction()?;
+
+    if tx.execute("DELETE FROM running", [])? == 0 {
+        return Ok(false);
+    }
+    tx.execute("DELETE FROM pauses WHERE task_id = ?1", [RUNNING_TASK_ID])?;
+    insert_entry(&tx, task, pauses)?;
+    tx.commit()?;
+
+    Ok(true)
+}
+
+fn insert_entry(tx: &rusqlite::Transaction, task: &Task, pauses: &[(DateTime<Local>, DateTime<Local>)]) -> Result<()> {
+    tx.execute(
+        "INSERT INTO tasks (task_name, start_time, stop_time, tags, pomodoros, project, billable)
+            values (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+        &[&task.task_name, &task.start_time, &task.stop_time, &task.tags,
+          &task.pomodoros.to_string(), &task.project, &(task.billable as i32).to_string()],
     )?;
+    let task_id = tx.last_insert_rowid();
+    for (pause_start, pause_stop) in pauses {
+        tx.execute(
+            "INSERT INTO pauses (task_id, start_time, stop_time) values (?1, ?2, ?3)",
+            &[&task_id.to_string(), &pause_start.to_rfc3339(), &pause_stop.to_rfc3339()],
+        )?;
+    }
 
     Ok(())
 }
 
+pub fn import_tasks(tasks: Vec<Task>) -> Result<usize> {
+    // Write imported tasks in one transaction, skipping any that already exist
+    let mut conn = open()?;
+    let tx = conn.transaction()?;
+    let mut imported = 0;
+
+    for task in tasks {
+        let exists: bool = tx.query_row(
+            "SELECT EXISTS(SELECT 1 FROM tasks WHERE task_name = (?1) AND start_time = (?2) AND stop_time = (?3))",
+            &[&task.task_name, &task.start_time, &task.stop_time],
+            |row| row.get(0),
+        )?;
+
+        if !exists {
+            tx.execute(
+                "INSERT INTO tasks (task_name, start_time, stop_time, tags, description) values (?1, ?2, ?3, ?4, ?5)",
+                &[&task.task_name, &task.start_time, &task.stop_time, &task.tags, &task.description],
+            )?;
+            imported += 1;
+        }
+    }
+
+    tx.commit()?;
+
+    Ok(imported)
+}
+
+pub fn task_names_and_tags() -> Result<Vec<(String, String)>> {
+    // Every different task once, the most recently started first
+    let conn = open()?;
+
+    let mut query = conn.prepare(
+        "SELECT task_name, tags FROM tasks GROUP BY task_name, tags ORDER BY max(start_time) DESC")?;
+    let names_and_tags = query.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+        .collect::<Result<Vec<(String, String)>>>()?;
+
+    Ok(names_and_tags)
+}
+
 pub fn retrieve() -> Result<Vec<Task>, rusqlite::Error> {
     // Retrieve all tasks from the database
-    let conn = Connection::open(get_directory())?;
+    let conn = open()?;
 
     let mut query = conn.prepare("SELECT * FROM tasks ORDER BY start_time")?;
     let task_iter = query.query_map([], |row| {
@@ -108,6 +517,11 @@ pub fn retrieve() -> Result<Vec<Task>, rusqlite::Error> {
             start_time: row.get(2)?,
             stop_time: row.get(3)?,
             tags: row.get(4)?,
+            description: row.get(5)?,
+            pomodoros: row.get(6)?,
+            project: row.get(7)?,
+            billable: row.get(8)?,
+            paused: 0,
         })
     })?;
 
@@ -115,6 +529,7 @@ pub fn retrieve() -> Result<Vec<Task>, rusqlite::Error> {
     for task_item in task_iter {
         tasks_vec.push(task_item.unwrap());
     }
+    add_paused_time(&conn, &mut tasks_vec)?;
 
     Ok(tasks_vec)
 
@@ -122,7 +537,7 @@ pub fn retrieve() -> Result<Vec<Task>, rusqlite::Error> {
 
 // pub fn retrieve_date_range() -> Result<Vec<Task>, rusqlite::Error> {
     // Retrieve all tasks from the database
-//     let conn = Connection::open(get_directory())?;
+//     let conn = open()?;
 
 //     let mut query = conn.prepare("SELECT * FROM tasks ORDER BY start_time")?;
 //     let task_iter = query.query_map([], |row| {
@@ -147,7 +562,7 @@ pub fn retrieve() -> Result<Vec<Task>, rusqlite::Error> {
 // }
 
 pub fn update_start_time(id: i32, start_time: String) -> Result<()> {
-    let conn = Connection::open(get_directory())?;
+    let conn = open()?;
 
     conn.execute(
         "UPDATE tasks SET start_time = (?1) WHERE id = (?2)",
@@ -158,7 +573,7 @@ pub fn update_start_time(id: i32, start_time: String) -> Result<()> {
 }
 
 pub fn update_stop_time(id: i32, stop_time: String) -> Result<()> {
-    let conn = Connection::open(get_directory())?;
+    let conn = open()?;
 
     conn.execute(
         "UPDATE tasks SET stop_time = (?1) WHERE id = (?2)",
@@ -169,7 +584,7 @@ pub fn update_stop_time(id: i32, stop_time: String) -> Result<()> {
 }
 
 pub fn update_task_name(id: i32, task_name: String) -> Result<()> {
-    let conn = Connection::open(get_directory())?;
+    let conn = open()?;
 
     conn.execute(
         "UPDATE tasks SET task_name = (?1) WHERE id = (?2)",
@@ -180,7 +595,7 @@ pub fn update_task_name(id: i32, task_name: String) -> Result<()> {
 }
 
 pub fn update_tags(id: i32, tags: String) -> Result<()> {
-    let conn = Connection::open(get_directory())?;
+    let conn = open()?;
 
     conn.execute(
         "UPDATE tasks SET tags = (?1) WHERE id = (?2)",
@@ -191,7 +606,7 @@ pub fn update_tags(id: i32, tags: String) -> Result<()> {
 }
 
 pub fn get_list_by_id(id_list: Vec<i32>) -> Result<Vec<Task>, rusqlite::Error> {
-    let conn = Connection::open(get_directory())?;
+    let conn = open()?;
     let mut tasks_vec: Vec<Task> = Vec::new();
 
     for id in id_list {
@@ -204,6 +619,11 @@ pub fn get_list_by_id(id_list: Vec<i32>) -> Result<Vec<Task>, rusqlite::Error> {
                 start_time: row.get(2)?,
                 stop_time: row.get(3)?,
                 tags: row.get(4)?,
+                description: row.get(5)?,
+                pomodoros: row.get(6)?,
+                project: row.get(7)?,
+                billable: row.get(8)?,
+                paused: 0,
             })
         })?;
 
@@ -211,12 +631,66 @@ pub fn get_list_by_id(id_list: Vec<i32>) -> Result<Vec<Task>, rusqlite::Error> {
             tasks_vec.push(task_item.unwrap());
         }
     }
+    add_paused_time(&conn, &mut tasks_vec)?;
 
     Ok(tasks_vec)
 }
 
+pub fn get_pauses(task_id: i32) -> Result<Vec<Pause>> {
+    let conn = open()?;
+
+    let mut query = conn.prepare(
+        "SELECT task_id, start_time, stop_time FROM pauses WHERE task_id = (?1) ORDER BY start_time")?;
+    let pause_iter = query.query_map(&[&task_id.to_string()], pause_from_row)?;
+    pause_iter.collect()
+}
+
+fn pause_from_row(row: &rusqlite::Row) -> Result<Pause> {
+    Ok(Pause {
+        task_id: row.get(0)?,
+        start_time: row.get(1)?,
+        stop_time: row.get(2)?,
+    })
+}
+
+pub fn pauses_by_task() -> Result<HashMap<i32, Vec<Pause>>> {
+    let conn = open()?;
+    read_pauses(&conn)
+}
+
+fn read_pauses(conn: &Connection) -> Result<HashMap<i32, Vec<Pause>>> {
+    // Every task's pauses in order. Databases from before pauses have no table.
+    let mut query = match conn.prepare("SELECT task_id, start_time, stop_time FROM pauses ORDER BY start_time") {
+        Ok(query) => query,
+        Err(_) => return Ok(HashMap::new()),
+    };
+    let mut pauses: HashMap<i32, Vec<Pause>> = HashMap::new();
+    for pause in query.query_map([], pause_from_row)? {
+        let pause = pause?;
+        pauses.entry(pause.task_id).or_default().push(pause);
+    }
+    Ok(pauses)
+}
+
+fn add_paused_time(conn: &Connection, tasks: &mut [Task]) -> Result<()> {
+    // Sum up each task's pauses
+    let pauses = read_pauses(conn)?;
+    for task in tasks {
+        task.paused = pauses.get(&task.id)
+            .into_iter()
+            .flatten()
+            .filter_map(|pause| {
+                let start = DateTime::parse_from_rfc3339(&pause.start_time).ok()?;
+                let stop = DateTime::parse_from_rfc3339(&pause.stop_time).ok()?;
+                Some((stop - start).num_seconds())
+            })
+            .sum();
+    }
+    Ok(())
+}
+
 pub fn check_for_tasks() -> Result<String> {
-    let conn = Connection::open(get_directory())?;
+    let conn = open()?;
 
     conn.query_row(
         "SELECT task_name FROM tasks ORDER BY ROWID ASC LIMIT 1",
@@ -226,28 +700,31 @@ pub fn check_for_tasks() -> Result<String> {
 }
 
 pub fn delete_by_ids(id_list: Vec<i32>) -> Result<()> {
-    let conn = Connection::open(get_directory())?;
+    let conn = open()?;
 
     for id in id_list {
         conn.execute("delete FROM tasks WHERE id = (?1)", &[&id.to_string()])?;
+        let _ = conn.execute("delete FROM pauses WHERE task_id = (?1)", &[&id.to_string()]);
     }
 
     Ok(())
 }
 
 pub fn delete_by_id(id: i32) -> Result<()> {
-    let conn = Connection::open(get_directory())?;
+    let conn = open()?;
 
     conn.execute("delete FROM tasks WHERE id = (?1)", &[&id.to_string()])?;
+    let _ = conn.execute("delete FROM pauses WHERE task_id = (?1)", &[&id.to_string()]);
 
     Ok(())
 }
 
 pub fn delete_all() -> Result<()> {
     // Delete everything from the database
-    let conn = Connection::open(get_directory())?;
+    let conn = open()?;
 
     conn.execute("delete from tasks",[],)?;
+    let _ = conn.execute("delete from pauses",[],);
 
     Ok(())
 }
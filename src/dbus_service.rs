@@ -0,0 +1,354 @@
+// Furtherance - Track your time without being tracked
+// Copyright (C) 2022  Ricky Kresslein <rk@lakoliu.com>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use chrono::{DateTime, Local};
+use gtk::prelude::*;
+use gtk::{gio, glib};
+use log::debug;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use crate::FurtheranceApplication;
+use crate::autosave;
+use crate::database;
+use crate::utils;
+
+const INTERFACE_NAME: &str = "com.lakoliu.Furtherance";
+const PROPERTIES_INTERFACE: &str = "org.freedesktop.DBus.Properties";
+
+const INTERFACE_XML: &str = r#"
+<node>
+  <interface name="com.lakoliu.Furtherance">
+    <method name="Start">
+      <arg name="task" type="s" direction="in"/>
+      <arg name="started" type="b" direction="out"/>
+    </method>
+    <method name="Stop">
+      <arg name="stopped" type="b" direction="out"/>
+    </method>
+    <method name="Toggle">
+      <arg name="running" type="b" direction="out"/>
+    </method>
+    <method name="GetStatus">
+      <arg name="running" type="b" direction="out"/>
+      <arg name="task" type="s" direction="out"/>
+      <arg name="start_time" type="x" direction="out"/>
+      <arg name="elapsed" type="x" direction="out"/>
+    </method>
+    <signal name="TimerStarted">
+      <arg name="task" type="s"/>
+      <arg name="start_time" type="x"/>
+    </signal>
+    <signal name="TimerStopped">
+      <arg name="task" type="s"/>
+      <arg name="duration" type="x"/>
+    </signal>
+    <signal name="Tick">
+      <arg name="elapsed" type="x"/>
+    </signal>
+    <property name="RunningTask" type="s" access="read"/>
+  </interface>
+</node>
+"#;
+
+// Where the interface's calls end up, the app or a stand-in in the tests
+pub trait Timer: Send + Sync + 'static {
+    // None when there is no timer window to ask
+    fn start_task(&self, task_text: &str) -> Option<bool>;
+    fn stop_task(&self) -> Option<bool>;
+    fn toggle_timer(&self) -> Option<bool>;
+    fn status(&self) -> (bool, String, i64, i64);
+}
+
+struct AppTimer;
+
+impl Timer for AppTimer {
+    // Calls arrive on the main loop, so the window can be used directly
+    fn start_task(&self, task_text: &str) -> Option<bool> {
+        Some(FurtheranceApplication::default().main_window()?.start_task(task_text))
+    }
+
+    fn stop_task(&self) -> Option<bool> {
+        Some(FurtheranceApplication::default().main_window()?.stop_task())
+    }
+
+    fn toggle_timer(&self) -> Option<bool> {
+        Some(FurtheranceApplication::default().main_window()?.toggle_timer())
+    }
+
+    fn status(&self) -> (bool, String, i64, i64) {
+        status()
+    }
+}
+
+pub fn register(app: &FurtheranceApplication) {
+    // Export the timer next to the GApplication interfaces on the app's bus name
+    let (connection, object_path) = match (app.dbus_connection(), app.dbus_object_path()) {
+        (Some(connection), Some(object_path)) => (connection, object_path),
+        _ => return,
+    };
+    if let Err(err) = export(&connection, &object_path, AppTimer) {
+        debug!("Could not export the D-Bus interface: {}", err);
+    }
+}
+
+fn export<T: Timer>(connection: &gio::DBusConnection,
+                    object_path: &str,
+                    timer: T) -> Result<gio::RegistrationId, glib::Error> {
+    let interface_info = gio::DBusNodeInfo::for_xml(INTERFACE_XML)?
+        .lookup_interface(INTERFACE_NAME)
+        .ok_or_else(|| glib::Error::new(gio::IOErrorEnum::NotFound, "No timer interface"))?;
+    let timer = Arc::new(timer);
+    let property_timer = timer.clone();
+
+    connection.register_object(
+        object_path,
+        &interface_info,
+        move |_, _, _, _, method_name, parameters, invocation| {
+            let answer = match method_name {
+                // Asking for the status shouldn't open a window, panels poll it every second
+                "GetStatus" => {
+                    invocation.return_value(Some(&timer.status().to_variant()));
+                    return;
+                }
+                "Start" => {
+                    let task_text = parameters.get::<(String,)>().map(|(task_text,)| task_text).unwrap_or_default();
+                    timer.start_task(&task_text)
+                }
+                "Stop" => timer.stop_task(),
+                "Toggle" => timer.toggle_timer(),
+                _ => {
+                    invocation.return_dbus_error("org.freedesktop.DBus.Error.UnknownMethod", method_name);
+                    return;
+                }
+            };
+            match answer {
+                Some(answer) => invocation.return_value(Some(&(answer,).to_variant())),
+                None => invocation.return_dbus_error("org.freedesktop.DBus.Error.Failed", "No timer window"),
+            }
+        },
+        move |_, _, _, _, property_name| {
+            match property_name {
+                "RunningTask" => property_timer.status().1.to_variant(),
+                _ => "".to_variant(),
+            }
+        },
+        |_, _, _, _, _, _| false,
+    )
+}
+
+fn status() -> (bool, String, i64, i64) {
+    // From the window if it is open, otherwise from the task left running in the database
+    if let Some(window) = FurtheranceApplication::default().open_window() {
+        return match window.timer_status() {
+            Some((task_text, start_time)) => {
+                (true, task_text, start_time.timestamp(), window.elapsed_time())
+            }
+            None => (false, String::new(), 0, 0),
+        };
+    }
+
+    match database::get_running() {
+        Ok(Some(running)) => match autosave::deserialize(&running.state) {
+            Some(saved) => {
+                let elapsed = saved.timer.elapsed(Local::now()).num_seconds();
+                (true, saved.task_text.clone(), saved.start_time().timestamp(), elapsed)
+            }
+            None => match DateTime::parse_from_rfc3339(&running.start_time) {
+                Ok(start_time) => {
+                    let elapsed = (Local::now() - start_time.with_timezone(&Local)).num_seconds();
+                    (true, utils::task_text(&running.task_name, &running.tags), start_time.timestamp(), elapsed)
+                }
+                Err(_) => (false, String::new(), 0, 0),
+            },
+        },
+        _ => (false, String::new(), 0, 0),
+    }
+}
+
+fn emit(interface_name: &str, signal_name: &str, parameters: glib::Variant) {
+    let app = FurtheranceApplication::default();
+    if let (Some(connection), Some(object_path)) = (app.dbus_connection(), app.dbus_object_path()) {
+        let _ = connection.emit_signal(None, &object_path, interface_name, signal_name, Some(&parameters));
+    }
+}
+
+pub fn running_task_changed(task_text: &str) {
+    let mut changed: HashMap<String, glib::Variant> = HashMap::new();
+    changed.insert("RunningTask".to_string(), task_text.to_variant());
+    emit(PROPERTIES_INTERFACE,
+         "PropertiesChanged",
+         (INTERFACE_NAME.to_string(), changed, Vec::<String>::new()).to_variant());
+}
+
+pub fn timer_started(task_text: &str, start_time: DateTime<Local>) {
+    emit(INTERFACE_NAME, "TimerStarted", (task_text.to_string(), start_time.timestamp()).to_variant());
+    running_task_changed(task_text);
+}
+
+pub fn timer_stopped(task_text: &str, duration: i64) {
+    emit(INTERFACE_NAME, "TimerStopped", (task_text.to_string(), duration).to_variant());
+    running_task_changed("");
+}
+
+pub fn tick(elapsed: i64) {
+    emit(INTERFACE_NAME, "Tick", (elapsed,).to_variant());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_bus::PrivateBus;
+    use dbus::blocking::{Connection, Proxy};
+    use std::sync::mpsc::{self, TryRecvError};
+    use std::sync::Mutex;
+    use std::thread;
+    use std::time::Duration;
+
+    const OBJECT_PATH: &str = "/com/lakoliu/Furtherance";
+
+    // Times one task at a time, like the window
+    #[derive(Default)]
+    struct FakeTimer {
+        task: Mutex<Option<String>>,
+    }
+
+    impl Timer for FakeTimer {
+        fn start_task(&self, task_text: &str) -> Option<bool> {
+            let mut task = self.task.lock().unwrap();
+            if task.is_some() || task_text.is_empty() {
+                return Some(false);
+            }
+            *task = Some(task_text.to_string());
+            Some(true)
+        }
+
+        fn stop_task(&self) -> Option<bool> {
+            Some(self.task.lock().unwrap().take().is_some())
+        }
+
+        fn toggle_timer(&self) -> Option<bool> {
+            if self.stop_task()? {
+                return Some(false);
+            }
+            self.start_task("Last task")
+        }
+
+        fn status(&self) -> (bool, String, i64, i64) {
+            match &*self.task.lock().unwrap() {
+                Some(task) => (true, task.clone(), 1_651_482_000, 90),
+                None => (false, String::new(), 0, 0),
+            }
+        }
+    }
+
+    // The app without a window, which can still answer GetStatus
+    struct NoWindow;
+
+    impl Timer for NoWindow {
+        fn start_task(&self, _: &str) -> Option<bool> {
+            None
+        }
+
+        fn stop_task(&self) -> Option<bool> {
+            None
+        }
+
+        fn toggle_timer(&self) -> Option<bool> {
+            None
+        }
+
+        fn status(&self) -> (bool, String, i64, i64) {
+            (true, "Write report #work".to_string(), 1_651_482_000, 90)
+        }
+    }
+
+    fn with_service<T: Timer>(timer: T, client: impl FnOnce(Proxy<&Connection>) + Send + 'static) {
+        // Export the timer on a private bus and call it from another thread
+        let bus = match PrivateBus::start() {
+            Some(bus) => bus,
+            None => return eprintln!("dbus-daemon is not installed, skipping"),
+        };
+        let context = glib::MainContext::new();
+        context.with_thread_default(|| {
+            let flags = gio::DBusConnectionFlags::AUTHENTICATION_CLIENT
+                | gio::DBusConnectionFlags::MESSAGE_BUS_CONNECTION;
+            let connection = gio::DBusConnection::for_address_sync(&bus.address, flags, None, gio::Cancellable::NONE)
+                .unwrap();
+            export(&connection, OBJECT_PATH, timer).unwrap();
+            let service_name = connection.unique_name().unwrap().to_string();
+
+            let client_connection = bus.connect();
+            let (done, finished) = mpsc::channel();
+            let client_thread = thread::spawn(move || {
+                client(client_connection.with_proxy(service_name, OBJECT_PATH, Duration::from_secs(5)));
+                done.send(()).unwrap();
+            });
+            // The calls are answered on this thread's main context
+            while let Err(TryRecvError::Empty) = finished.try_recv() {
+                context.iteration(false);
+                thread::sleep(Duration::from_millis(5));
+            }
+            client_thread.join().unwrap();
+        }).unwrap();
+    }
+
+    #[test]
+    fn start_and_stop() {
+        with_service(FakeTimer::default(), |proxy| {
+            let (started,): (bool,) = proxy.method_call(INTERFACE_NAME, "Start", ("Write report #work",)).unwrap();
+            assert!(started);
+            let (started,): (bool,) = proxy.method_call(INTERFACE_NAME, "Start", ("Other task",)).unwrap();
+            assert!(!started);
+            let (stopped,): (bool,) = proxy.method_call(INTERFACE_NAME, "Stop", ()).unwrap();
+            assert!(stopped);
+            let (stopped,): (bool,) = proxy.method_call(INTERFACE_NAME, "Stop", ()).unwrap();
+            assert!(!stopped);
+        });
+    }
+
+    #[test]
+    fn toggle() {
+        with_service(FakeTimer::default(), |proxy| {
+            let (running,): (bool,) = proxy.method_call(INTERFACE_NAME, "Toggle", ()).unwrap();
+            assert!(running);
+            let (running,): (bool,) = proxy.method_call(INTERFACE_NAME, "Toggle", ()).unwrap();
+            assert!(!running);
+        });
+    }
+
+    #[test]
+    fn get_status() {
+        with_service(FakeTimer::default(), |proxy| {
+            let status: (bool, String, i64, i64) = proxy.method_call(INTERFACE_NAME, "GetStatus", ()).unwrap();
+            assert_eq!(status, (false, String::new(), 0, 0));
+
+            let _: (bool,) = proxy.method_call(INTERFACE_NAME, "Start", ("Write report #work",)).unwrap();
+            let status: (bool, String, i64, i64) = proxy.method_call(INTERFACE_NAME, "GetStatus", ()).unwrap();
+            assert_eq!(status, (true, "Write report #work".to_string(), 1_651_482_000, 90));
+        });
+    }
+
+    #[test]
+    fn status_without_a_window() {
+        with_service(NoWindow, |proxy| {
+            let status: (bool, String, i64, i64) = proxy.method_call(INTERFACE_NAME, "GetStatus", ()).unwrap();
+            assert!(status.0);
+            let started: Result<(bool,), dbus::Error> = proxy.method_call(INTERFACE_NAME, "Start", ("Task",));
+            assert_eq!(started.unwrap_err().name(), Some("org.freedesktop.DBus.Error.Failed"));
+        });
+    }
+}
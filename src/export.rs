@@ -0,0 +1,75 @@
+// Furtherance - Track your time without being tracked
+// Copyright (C) 2022  Ricky Kresslein <rk@lakoliu.com>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+mod html;
+mod markdown;
+mod ods;
+mod org;
+mod print;
+mod timeclock;
+
+pub use html::to_html;
+pub use markdown::to_markdown;
+pub use ods::to_ods;
+pub use org::to_org;
+pub use print::{export_pdf, print_report};
+pub use timeclock::to_timeclock;
+
+use chrono::{DateTime, Local};
+use std::collections::HashMap;
+
+use crate::database::{Pause, Task};
+
+// A row of the report tree, groups have a depth of 0 and their tasks or tags 1
+pub struct ReportRow {
+    pub depth: u32,
+    pub label: String,
+    pub duration: i64,
+}
+
+// The report as it is shown in the report window
+pub struct ReportSummary {
+    pub date_range: String,
+    pub filter: String,
+    pub rows: Vec<ReportRow>,
+    pub total: i64,
+}
+
+fn worked_intervals(task: &Task, pauses: &HashMap<i32, Vec<Pause>>) -> Vec<(DateTime<Local>, DateTime<Local>)> {
+    // The times between a task's pauses, for formats that only know start and stop times
+    let start = DateTime::parse_from_rfc3339(&task.start_time).unwrap().with_timezone(&Local);
+    let stop = DateTime::parse_from_rfc3339(&task.stop_time).unwrap().with_timezone(&Local);
+
+    let mut intervals = Vec::new();
+    let mut interval_start = start;
+    for pause in pauses.get(&task.id).into_iter().flatten() {
+        let (pause_start, pause_stop) = match (DateTime::parse_from_rfc3339(&pause.start_time),
+                                               DateTime::parse_from_rfc3339(&pause.stop_time)) {
+            (Ok(pause_start), Ok(pause_stop)) => {
+                (pause_start.with_timezone(&Local).clamp(start, stop), pause_stop.with_timezone(&Local).clamp(start, stop))
+            }
+            _ => continue,
+        };
+        if pause_start > interval_start {
+            intervals.push((interval_start, pause_start));
+        }
+        interval_start = interval_start.max(pause_stop);
+    }
+    if stop > interval_start || intervals.is_empty() {
+        intervals.push((interval_start, stop.max(interval_start)));
+    }
+    intervals
+}
@@ -0,0 +1,167 @@
+// Furtherance - Track your time without being tracked
+// Copyright (C) 2022  Ricky Kresslein <rk@lakoliu.com>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use chrono::{Date, DateTime, Duration, Local};
+use gettextrs::*;
+use gtk::glib;
+use itertools::Itertools;
+
+use crate::database::Task;
+use crate::utils;
+
+const CHART_WIDTH: f64 = 720.0;
+const CHART_HEIGHT: f64 = 200.0;
+const CHART_MARGIN: f64 = 40.0;
+
+// Everything is inline so the file can be sent on its own
+const STYLE: &str = "
+body { font-family: Cantarell, 'Segoe UI', Helvetica, Arial, sans-serif; color: #241f31; max-width: 800px; margin: 2em auto; padding: 0 1em; }
+h1 { margin-bottom: 0; }
+.subtitle { color: #5e5c64; margin-top: 0.3em; }
+.total { font-size: 1.4em; font-weight: bold; }
+table { border-collapse: collapse; width: 100%; margin-bottom: 2em; }
+th, td { text-align: left; padding: 4px 8px; border-bottom: 1px solid #deddda; }
+td.duration, th.duration { text-align: right; font-variant-numeric: tabular-nums; white-space: nowrap; }
+tr.sub td { color: #5e5c64; padding-left: 2em; }
+.bar { background: #3584e4; height: 8px; border-radius: 4px; }
+svg { width: 100%; height: auto; margin-bottom: 2em; }
+svg text { font-size: 11px; fill: #5e5c64; }
+svg rect { fill: #3584e4; }
+";
+
+pub fn to_html(tasks: &[(Task, i64)], start_date: Date<Local>, end_date: Date<Local>) -> String {
+    let total_time: i64 = tasks.iter().map(|(_, duration)| duration).sum();
+    let date_range = format!("{} – {}", start_date.format("%b %e, %Y"), end_date.format("%b %e, %Y"));
+
+    let mut html = String::from("<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n");
+    html.push_str(&format!("<title>{} {}</title>\n", escape(&gettext("Furtherance Report")), date_range));
+    html.push_str(&format!("<style>{}</style>\n</head>\n<body>\n", STYLE));
+    html.push_str(&format!("<h1>{}</h1>\n<p class=\"subtitle\">{}</p>\n", escape(&gettext("Furtherance Report")), date_range));
+    html.push_str(&format!("<p class=\"total\">{}: {}</p>\n",
+                           escape(&gettext("Total")),
+                           utils::format_duration(total_time, true)));
+
+    html.push_str(&format!("<h2>{}</h2>\n", escape(&gettext("Time per day"))));
+    html.push_str(&daily_chart(tasks, start_date, end_date));
+
+    // The same totals the report window shows
+    html.push_str(&format!("<h2>{}</h2>\n", escape(&gettext("Tasks"))));
+    html.push_str(&totals_table(utils::durations_by_task(tasks.to_vec()), total_time));
+    html.push_str(&format!("<h2>{}</h2>\n", escape(&gettext("Tags"))));
+    html.push_str(&totals_table(utils::durations_by_tag(tasks.to_vec()), total_time));
+
+    html.push_str(&format!("<h2>{}</h2>\n", escape(&gettext("Entries"))));
+    html.push_str(&entries_table(tasks));
+
+    html.push_str("</body>\n</html>\n");
+    html
+}
+
+fn totals_table(groups: utils::GroupedDurations, total_time: i64) -> String {
+    let mut table = String::from("<table>\n");
+    for (mut name, duration, children) in groups {
+        if name == "#" {
+            name = gettext("no tags");
+        }
+        let percent = if total_time > 0 { duration as f64 / total_time as f64 * 100.0 } else { 0.0 };
+        table.push_str(&format!("<tr><td><strong>{}</strong><div class=\"bar\" style=\"width: {:.1}%\"></div></td><td class=\"duration\">{}</td></tr>\n",
+                                escape(&name),
+                                percent,
+                                utils::format_duration(duration, true)));
+        for (child, child_duration) in children {
+            table.push_str(&format!("<tr class=\"sub\"><td>{}</td><td class=\"duration\">{}</td></tr>\n",
+                                    escape(&child),
+                                    utils::format_duration(child_duration, true)));
+        }
+    }
+    table.push_str("</table>\n");
+    table
+}
+
+fn daily_chart(tasks: &[(Task, i64)], start_date: Date<Local>, end_date: Date<Local>) -> String {
+    // One bar per day of the range, including days without any tasks
+    let mut days: Vec<(Date<Local>, i64)> = Vec::new();
+    let mut date = start_date;
+    while date <= end_date {
+        days.push((date, 0));
+        date = date + Duration::days(1);
+    }
+    for (task, duration) in tasks {
+        let start = DateTime::parse_from_rfc3339(&task.start_time).unwrap().with_timezone(&Local);
+        if let Some(day) = days.iter_mut().find(|(date, _)| *date == start.date()) {
+            day.1 += duration;
+        }
+    }
+
+    let max_duration = days.iter().map(|(_, duration)| *duration).max().unwrap_or(0).max(3600);
+    // Round the scale up to whole hours
+    let max_hours = (max_duration + 3599) / 3600;
+    let plot_width = CHART_WIDTH - CHART_MARGIN;
+    let plot_height = CHART_HEIGHT - CHART_MARGIN;
+    let bar_width = plot_width / days.len().max(1) as f64;
+    // Label about every tenth bar so the dates don't overlap
+    let label_every = (days.len() / 10).max(1);
+
+    let mut svg = format!("<svg viewBox=\"0 0 {} {}\" xmlns=\"http://www.w3.org/2000/svg\">\n", CHART_WIDTH, CHART_HEIGHT);
+    svg.push_str(&format!("<text x=\"0\" y=\"12\">{}h</text>\n<text x=\"0\" y=\"{}\">0h</text>\n",
+                          max_hours,
+                          plot_height));
+    for (i, (date, duration)) in days.iter().enumerate() {
+        let height = *duration as f64 / (max_hours * 3600) as f64 * plot_height;
+        let x = CHART_MARGIN + i as f64 * bar_width;
+        svg.push_str(&format!("<rect x=\"{:.1}\" y=\"{:.1}\" width=\"{:.1}\" height=\"{:.1}\"><title>{}: {}</title></rect>\n",
+                              x + bar_width * 0.1,
+                              plot_height - height,
+                              bar_width * 0.8,
+                              height,
+                              date.format("%a, %b %e"),
+                              utils::format_duration(*duration, true)));
+        if i % label_every == 0 {
+            svg.push_str(&format!("<text x=\"{:.1}\" y=\"{}\">{}</text>\n",
+                                  x,
+                                  CHART_HEIGHT - 20.0,
+                                  date.format("%b %e")));
+        }
+    }
+    svg.push_str("</svg>\n");
+    svg
+}
+
+fn entries_table(tasks: &[(Task, i64)]) -> String {
+    let mut table = format!("<table>\n<tr><th>{}</th><th>{}</th><th>{}</th><th class=\"duration\">{}</th></tr>\n",
+                            escape(&gettext("Date")),
+                            escape(&gettext("Task")),
+                            escape(&gettext("Tags")),
+                            escape(&gettext("Duration")));
+    for (task, duration) in tasks.iter().sorted_by(|(a, _), (b, _)| a.start_time.cmp(&b.start_time)) {
+        let start = DateTime::parse_from_rfc3339(&task.start_time).unwrap().with_timezone(&Local);
+        let stop = DateTime::parse_from_rfc3339(&task.stop_time).unwrap().with_timezone(&Local);
+        let tags = if task.tags.trim().is_empty() { String::new() } else { format!("#{}", task.tags) };
+        table.push_str(&format!("<tr><td>{} {}–{}</td><td>{}</td><td>{}</td><td class=\"duration\">{}</td></tr>\n",
+                                start.format("%Y-%m-%d"),
+                                start.format("%H:%M"),
+                                stop.format("%H:%M"),
+                                escape(&task.task_name),
+                                escape(&tags),
+                                utils::format_duration(*duration, true)));
+    }
+    table.push_str("</table>\n");
+    table
+}
+
+fn escape(text: &str) -> glib::GString {
+    glib::markup_escape_text(text)
+}
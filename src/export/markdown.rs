@@ -0,0 +1,77 @@
+// Furtherance - Track your time without being tracked
+// Copyright (C) 2022  Ricky Kresslein <rk@lakoliu.com>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use chrono::{Date, Local};
+use gettextrs::*;
+use std::collections::HashMap;
+
+use crate::database::Task;
+use crate::utils;
+
+pub fn to_markdown(tasks: &[(Task, i64)],
+                   start_date: Date<Local>,
+                   end_date: Date<Local>,
+                   show_seconds: bool) -> String {
+    // A table for each day with the time spent on every task, oldest day first
+    let mut sorted_tasks: Vec<Task> = tasks.iter().map(|(task, _)| task.clone()).collect();
+    sorted_tasks.sort_by(|a, b| a.start_time.cmp(&b.start_time));
+    let durations: HashMap<i32, i64> = tasks.iter().map(|(task, duration)| (task.id, *duration)).collect();
+
+    let mut markdown = format!("# {} {} – {}\n",
+                               gettext("Timesheet"),
+                               start_date.format("%b %e, %Y"),
+                               end_date.format("%b %e, %Y"));
+    let mut total_time: i64 = 0;
+
+    for (date, day_tasks) in utils::group_tasks_by_day(sorted_tasks) {
+        // Tasks with the same name and tags share a row, like in the task list
+        let mut rows: Vec<(String, String, i64)> = Vec::new();
+        for task in &day_tasks {
+            let duration = durations[&task.id];
+            match rows.iter_mut().find(|(name, tags, _)| name == &task.task_name && tags == &task.tags) {
+                Some(row) => row.2 += duration,
+                None => rows.push((task.task_name.clone(), task.tags.clone(), duration)),
+            }
+        }
+
+        markdown.push_str(&format!("\n## {}\n\n", date.format("%A, %b %e")));
+        markdown.push_str(&format!("| {} | {} | {} |\n", gettext("Task"), gettext("Tags"), gettext("Duration")));
+        markdown.push_str("| --- | --- | ---: |\n");
+        let mut day_total_time: i64 = 0;
+        for (task_name, tags, duration) in rows {
+            let tags = if tags.trim().is_empty() { String::new() } else { format!("#{}", tags) };
+            markdown.push_str(&format!("| {} | {} | {} |\n",
+                                       escape(&task_name),
+                                       escape(&tags),
+                                       utils::format_duration(duration, show_seconds)));
+            day_total_time += duration;
+        }
+        markdown.push_str(&format!("| **{}** | | **{}** |\n",
+                                   gettext("Total"),
+                                   utils::format_duration(day_total_time, show_seconds)));
+        total_time += day_total_time;
+    }
+
+    markdown.push_str(&format!("\n**{}: {}**\n",
+                               gettext("Total"),
+                               utils::format_duration(total_time, show_seconds)));
+    markdown
+}
+
+fn escape(text: &str) -> String {
+    // Pipes would end the table cell early
+    text.replace('|', "\\|")
+}
@@ -0,0 +1,252 @@
+// Furtherance - Track your time without being tracked
+// Copyright (C) 2022  Ricky Kresslein <rk@lakoliu.com>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use chrono::{DateTime, Local};
+use gettextrs::*;
+use itertools::Itertools;
+use std::io::{Cursor, Write};
+use zip::write::FileOptions;
+use zip::{CompressionMethod, ZipWriter};
+
+use crate::database::Task;
+use crate::export::ReportSummary;
+
+const MIMETYPE: &str = "application/vnd.oasis.opendocument.spreadsheet";
+
+const MANIFEST: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<manifest:manifest xmlns:manifest="urn:oasis:names:tc:opendocument:xmlns:manifest:1.0" manifest:version="1.2">
+ <manifest:file-entry manifest:full-path="/" manifest:version="1.2" manifest:media-type="application/vnd.oasis.opendocument.spreadsheet"/>
+ <manifest:file-entry manifest:full-path="content.xml" manifest:media-type="text/xml"/>
+</manifest:manifest>
+"#;
+
+// Durations are shown as [h]:mm so that days don't wrap around,
+// start and stop times as dates with the time of day
+const CONTENT_START: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<office:document-content xmlns:office="urn:oasis:names:tc:opendocument:xmlns:office:1.0" xmlns:style="urn:oasis:names:tc:opendocument:xmlns:style:1.0" xmlns:text="urn:oasis:names:tc:opendocument:xmlns:text:1.0" xmlns:table="urn:oasis:names:tc:opendocument:xmlns:table:1.0" xmlns:fo="urn:oasis:names:tc:opendocument:xmlns:xsl-fo-compatible:1.0" xmlns:number="urn:oasis:names:tc:opendocument:xmlns:datastyle:1.0" office:version="1.2">
+ <office:automatic-styles>
+  <number:time-style style:name="N_duration" number:truncate-on-overflow="false">
+   <number:hours/><number:text>:</number:text><number:minutes number:style="long"/>
+  </number:time-style>
+  <number:date-style style:name="N_date_time">
+   <number:year number:style="long"/><number:text>-</number:text><number:month number:style="long"/><number:text>-</number:text><number:day number:style="long"/><number:text> </number:text><number:hours number:style="long"/><number:text>:</number:text><number:minutes number:style="long"/>
+  </number:date-style>
+  <style:style style:name="duration" style:family="table-cell" style:data-style-name="N_duration"/>
+  <style:style style:name="duration_bold" style:family="table-cell" style:data-style-name="N_duration">
+   <style:text-properties fo:font-weight="bold"/>
+  </style:style>
+  <style:style style:name="date_time" style:family="table-cell" style:data-style-name="N_date_time"/>
+  <style:style style:name="bold" style:family="table-cell">
+   <style:text-properties fo:font-weight="bold"/>
+  </style:style>
+  <style:style style:name="wide" style:family="table-column">
+   <style:table-column-properties style:column-width="2in"/>
+  </style:style>
+ </office:automatic-styles>
+ <office:body>
+  <office:spreadsheet>
+"#;
+
+const CONTENT_END: &str = "  </office:spreadsheet>
+ </office:body>
+</office:document-content>
+";
+
+pub fn to_ods(summary: &ReportSummary, tasks: &[(Task, i64)]) -> zip::result::ZipResult<Vec<u8>> {
+    // A summary sheet like the report window and a sheet with every entry
+    let mut content = CONTENT_START.to_string();
+    content.push_str(&summary_sheet(summary));
+    content.push_str(&entries_sheet(tasks));
+    content.push_str(CONTENT_END);
+
+    // The mimetype has to come first and stay uncompressed
+    let mut archive = ZipWriter::new(Cursor::new(Vec::new()));
+    archive.start_file("mimetype", FileOptions::default().compression_method(CompressionMethod::Stored))?;
+    archive.write_all(MIMETYPE.as_bytes())?;
+    archive.start_file("META-INF/manifest.xml", FileOptions::default())?;
+    archive.write_all(MANIFEST.as_bytes())?;
+    archive.start_file("content.xml", FileOptions::default())?;
+    archive.write_all(content.as_bytes())?;
+    Ok(archive.finish()?.into_inner())
+}
+
+fn summary_sheet(summary: &ReportSummary) -> String {
+    let mut sheet = format!("   <table:table table:name=\"{}\">\n", escape(&gettext("Summary")));
+    sheet.push_str("    <table:table-column table:style-name=\"wide\" table:number-columns-repeated=\"2\"/>\n");
+    sheet.push_str("    <table:table-column/>\n");
+
+    sheet.push_str(&row(&[text_cell(&summary.date_range)]));
+    sheet.push_str(&row(&[text_cell(&summary.filter)]));
+    sheet.push_str(&row(&[]));
+    sheet.push_str(&row(&[
+        bold_cell(&gettext("Group")),
+        bold_cell(&gettext("Task or tag")),
+        bold_cell(&gettext("Duration")),
+    ]));
+
+    for report_row in &summary.rows {
+        // Groups take the first column and their tasks or tags the second
+        let cells = if report_row.depth == 0 {
+            [bold_cell(&report_row.label), empty_cell(), duration_cell(report_row.duration, "duration_bold")]
+        } else {
+            [empty_cell(), text_cell(&report_row.label), duration_cell(report_row.duration, "duration")]
+        };
+        sheet.push_str(&row(&cells));
+    }
+
+    sheet.push_str(&row(&[
+        bold_cell(&gettext("Total")),
+        empty_cell(),
+        duration_cell(summary.total, "duration_bold"),
+    ]));
+    sheet.push_str("   </table:table>\n");
+    sheet
+}
+
+fn entries_sheet(tasks: &[(Task, i64)]) -> String {
+    let mut sheet = format!("   <table:table table:name=\"{}\">\n", escape(&gettext("Entries")));
+    sheet.push_str("    <table:table-column table:style-name=\"wide\" table:number-columns-repeated=\"5\"/>\n");
+    sheet.push_str("    <table:table-column/>\n");
+    sheet.push_str(&row(&[
+        bold_cell(&gettext("Start")),
+        bold_cell(&gettext("Stop")),
+        bold_cell(&gettext("Task")),
+        bold_cell(&gettext("Tags")),
+        bold_cell(&gettext("Description")),
+        bold_cell(&gettext("Duration")),
+    ]));
+
+    for (task, duration) in tasks.iter().sorted_by(|(a, _), (b, _)| a.start_time.cmp(&b.start_time)) {
+        let start = DateTime::parse_from_rfc3339(&task.start_time).unwrap().with_timezone(&Local);
+        let stop = DateTime::parse_from_rfc3339(&task.stop_time).unwrap().with_timezone(&Local);
+        let tags = if task.tags.trim().is_empty() { String::new() } else { format!("#{}", task.tags) };
+        sheet.push_str(&row(&[
+            date_time_cell(start),
+            date_time_cell(stop),
+            text_cell(&task.task_name),
+            text_cell(&tags),
+            text_cell(&task.description),
+            duration_cell(*duration, "duration"),
+        ]));
+    }
+
+    sheet.push_str("   </table:table>\n");
+    sheet
+}
+
+fn row(cells: &[String]) -> String {
+    format!("    <table:table-row>{}</table:table-row>\n", cells.join(""))
+}
+
+fn empty_cell() -> String {
+    "<table:table-cell/>".to_string()
+}
+
+fn text_cell(text: &str) -> String {
+    format!("<table:table-cell office:value-type=\"string\"><text:p>{}</text:p></table:table-cell>",
+            escape(text))
+}
+
+fn bold_cell(text: &str) -> String {
+    format!("<table:table-cell table:style-name=\"bold\" office:value-type=\"string\"><text:p>{}</text:p></table:table-cell>",
+            escape(text))
+}
+
+fn duration_cell(duration: i64, style: &str) -> String {
+    // A real time value, so it can be summed and multiplied by a rate
+    let (h, m, s) = (duration / 3600, duration % 3600 / 60, duration % 60);
+    format!("<table:table-cell table:style-name=\"{}\" office:value-type=\"time\" office:time-value=\"PT{}H{:02}M{:02}S\"><text:p>{}:{:02}</text:p></table:table-cell>",
+            style, h, m, s, h, m)
+}
+
+fn date_time_cell(date_time: DateTime<Local>) -> String {
+    format!("<table:table-cell table:style-name=\"date_time\" office:value-type=\"date\" office:date-value=\"{}\"><text:p>{}</text:p></table:table-cell>",
+            date_time.format("%Y-%m-%dT%H:%M:%S"),
+            date_time.format("%Y-%m-%d %H:%M"))
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Read;
+    use zip::ZipArchive;
+
+    use crate::export::ReportRow;
+
+    fn task(task_name: &str, tags: &str) -> Task {
+        Task {
+            id: 1,
+            task_name: task_name.to_string(),
+            start_time: "2022-05-02T09:00:00+00:00".to_string(),
+            stop_time: "2022-05-02T10:30:00+00:00".to_string(),
+            tags: tags.to_string(),
+            description: String::new(),
+            pomodoros: 0,
+            project: String::new(),
+            billable: false,
+            paused: 0,
+        }
+    }
+
+    #[test]
+    fn opens_as_a_zip_archive() {
+        let summary = ReportSummary {
+            date_range: "May 2, 2022".to_string(),
+            filter: String::new(),
+            rows: vec![ReportRow { depth: 0, label: "Report <draft> & notes".to_string(), duration: 5400 }],
+            total: 5400,
+        };
+        let ods = to_ods(&summary, &[(task("Write report", "work"), 5400)]).unwrap();
+        let mut archive = ZipArchive::new(Cursor::new(ods)).unwrap();
+
+        let names: Vec<String> = (0..archive.len())
+            .map(|i| archive.by_index(i).unwrap().name().to_string())
+            .collect();
+        assert_eq!(names, ["mimetype", "META-INF/manifest.xml", "content.xml"]);
+
+        let mut mimetype = archive.by_index(0).unwrap();
+        assert_eq!(mimetype.compression(), CompressionMethod::Stored);
+        let mut text = String::new();
+        mimetype.read_to_string(&mut text).unwrap();
+        assert_eq!(text, MIMETYPE);
+        drop(mimetype);
+
+        let mut content = String::new();
+        archive.by_name("content.xml").unwrap().read_to_string(&mut content).unwrap();
+        assert!(content.starts_with("<?xml"));
+        assert!(content.contains("Report &lt;draft&gt; &amp; notes"));
+        assert!(content.contains("<text:p>Write report</text:p>"));
+        assert!(content.contains("<text:p>#work</text:p>"));
+        assert!(content.contains("office:time-value=\"PT1H30M00S\""));
+    }
+
+    #[test]
+    fn writes_an_empty_report() {
+        let summary = ReportSummary { date_range: String::new(), filter: String::new(), rows: Vec::new(), total: 0 };
+        let ods = to_ods(&summary, &[]).unwrap();
+        let mut archive = ZipArchive::new(Cursor::new(ods)).unwrap();
+        let mut content = String::new();
+        archive.by_name("content.xml").unwrap().read_to_string(&mut content).unwrap();
+        assert!(content.ends_with("</office:document-content>\n"));
+    }
+}
@@ -0,0 +1,71 @@
+// Furtherance - Track your time without being tracked
+// Copyright (C) 2022  Ricky Kresslein <rk@lakoliu.com>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use chrono::{Date, Local};
+use itertools::Itertools;
+use std::collections::HashMap;
+
+use crate::database::{Pause, Task};
+use crate::export::worked_intervals;
+use crate::quick_entry;
+
+const CLOCK_FORMAT: &str = "%Y-%m-%d %a %H:%M";
+
+pub fn to_org(tasks: &[(Task, i64)],
+              pauses: &HashMap<i32, Vec<Pause>>,
+              start_date: Date<Local>,
+              end_date: Date<Local>) -> String {
+    // One heading per task and tags, with a CLOCK line for every time it was tracked between pauses
+    let mut tasks_by_name: Vec<(&Task, Vec<&Task>)> = Vec::new();
+    for (task, _) in tasks.iter().sorted_by(|(a, _), (b, _)| a.start_time.cmp(&b.start_time)) {
+        match tasks_by_name.iter_mut().find(|(first, _)| {
+            first.task_name == task.task_name && first.tags == task.tags
+        }) {
+            Some((_, same_name)) => same_name.push(task),
+            None => tasks_by_name.push((task, vec![task])),
+        }
+    }
+
+    let mut org = format!("#+TITLE: Furtherance {} – {}\n",
+                          start_date.format("%Y-%m-%d"),
+                          end_date.format("%Y-%m-%d"));
+
+    for (first, same_name) in tasks_by_name {
+        org.push_str(&format!("\n* {}", first.task_name));
+        let tags: Vec<String> = quick_entry::split_tags(&first.tags)
+            .iter()
+            .map(|x| x.replace(char::is_whitespace, "_"))
+            .collect();
+        if !tags.is_empty() {
+            org.push_str(&format!(" :{}:", tags.join(":")));
+        }
+        org.push_str("\n  :LOGBOOK:\n");
+
+        // Org lists the most recent clock first
+        let clocks = same_name.iter().flat_map(|task| worked_intervals(task, pauses));
+        for (start, stop) in clocks.collect::<Vec<_>>().into_iter().rev() {
+            let minutes = (stop - start).num_minutes();
+            org.push_str(&format!("  CLOCK: [{}]--[{}] => {:>2}:{:02}\n",
+                                  start.format(CLOCK_FORMAT),
+                                  stop.format(CLOCK_FORMAT),
+                                  minutes / 60,
+                                  minutes % 60));
+        }
+        org.push_str("  :END:\n");
+    }
+
+    org
+}
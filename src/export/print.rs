@@ -0,0 +1,183 @@
+// Furtherance - Track your time without being tracked
+// Copyright (C) 2022  Ricky Kresslein <rk@lakoliu.com>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use gettextrs::*;
+use glib::clone;
+use gtk::{cairo, glib, prelude::*};
+use std::path::Path;
+use std::rc::Rc;
+
+use crate::export::{ReportRow, ReportSummary};
+use crate::utils;
+
+const FONT_FAMILY: &str = "Sans";
+const TITLE_SIZE: f64 = 18.0;
+const FONT_SIZE: f64 = 10.0;
+const LINE_HEIGHT: f64 = 16.0;
+const HEADER_HEIGHT: f64 = 76.0;
+const INDENT: f64 = 18.0;
+const DURATION_GAP: f64 = 24.0;
+
+pub fn print_report(report: &Rc<ReportSummary>, parent: &impl IsA<gtk::Window>) -> Result<(), glib::Error> {
+    // Let the user pick a printer, or "Print to File" for a PDF
+    print_operation(report).run(gtk::PrintOperationAction::PrintDialog, Some(parent))?;
+    Ok(())
+}
+
+pub fn export_pdf(report: &Rc<ReportSummary>, path: &Path) -> Result<(), glib::Error> {
+    let operation = print_operation(report);
+    operation.set_export_filename(path);
+    operation.run(gtk::PrintOperationAction::Export, None::<&gtk::Window>)?;
+    Ok(())
+}
+
+fn print_operation(report: &Rc<ReportSummary>) -> gtk::PrintOperation {
+    let operation = gtk::PrintOperation::new();
+    operation.set_job_name(&gettext("Furtherance Report"));
+    operation.set_unit(gtk::Unit::Points);
+
+    operation.connect_begin_print(clone!(@strong report => move |operation, context| {
+        let (first_page, other_pages) = lines_per_page(context.height());
+        // Every row plus the total at the end
+        let lines = report.rows.len().max(1) + 1;
+        // div_ceil is newer than the Rust in the Flatpak SDK
+        #[allow(clippy::manual_div_ceil)]
+        let n_pages = if lines <= first_page {
+            1
+        } else {
+            1 + (lines - first_page + other_pages - 1) / other_pages
+        };
+        operation.set_n_pages(n_pages as i32);
+    }));
+
+    operation.connect_draw_page(clone!(@strong report => move |_, context, page_nr| {
+        // Cairo errors leave the page blank, there is nothing else to do here
+        let _ = draw_page(&report, context, page_nr as usize);
+    }));
+
+    operation
+}
+
+fn lines_per_page(page_height: f64) -> (usize, usize) {
+    // The first page starts below the header
+    let first_page = ((page_height - HEADER_HEIGHT) / LINE_HEIGHT).floor().max(1.0) as usize;
+    let other_pages = (page_height / LINE_HEIGHT).floor().max(1.0) as usize;
+    (first_page, other_pages)
+}
+
+fn draw_page(report: &ReportSummary,
+             context: &gtk::PrintContext,
+             page_nr: usize) -> Result<(), cairo::Error> {
+    let cr = context.cairo_context();
+    let width = context.width();
+    let (first_page, other_pages) = lines_per_page(context.height());
+    cr.set_source_rgb(0.0, 0.0, 0.0);
+
+    let mut y = 0.0;
+    let (first_line, last_line) = if page_nr == 0 {
+        y = draw_header(report, &cr, width)?;
+        (0, first_page)
+    } else {
+        let first_line = first_page + (page_nr - 1) * other_pages;
+        (first_line, first_line + other_pages)
+    };
+
+    let no_results = [ReportRow {
+        depth: 0,
+        label: gettext("No Results"),
+        duration: 0,
+    }];
+    let rows: &[ReportRow] = if report.rows.is_empty() { &no_results } else { &report.rows };
+
+    for row in rows.iter().take(last_line).skip(first_line) {
+        let weight = if row.depth == 0 { cairo::FontWeight::Bold } else { cairo::FontWeight::Normal };
+        let duration = if report.rows.is_empty() { String::new() } else { utils::format_duration(row.duration, true) };
+        y += LINE_HEIGHT;
+        draw_row(&cr, width, y, row.depth as f64 * INDENT, &row.label, &duration, weight)?;
+    }
+
+    // The total goes after the last row, with a line above it
+    if (first_line..last_line).contains(&rows.len()) {
+        y += LINE_HEIGHT;
+        cr.set_line_width(0.5);
+        cr.move_to(0.0, y - LINE_HEIGHT + 4.0);
+        cr.line_to(width, y - LINE_HEIGHT + 4.0);
+        cr.stroke()?;
+        draw_row(&cr,
+                 width,
+                 y,
+                 0.0,
+                 &gettext("Total"),
+                 &utils::format_duration(report.total, true),
+                 cairo::FontWeight::Bold)?;
+    }
+
+    Ok(())
+}
+
+fn draw_header(report: &ReportSummary, cr: &cairo::Context, width: f64) -> Result<f64, cairo::Error> {
+    // Title, date range and filter, returning where the rows start
+    cr.select_font_face(FONT_FAMILY, cairo::FontSlant::Normal, cairo::FontWeight::Bold);
+    cr.set_font_size(TITLE_SIZE);
+    cr.move_to(0.0, TITLE_SIZE);
+    cr.show_text(&gettext("Furtherance Report"))?;
+
+    cr.select_font_face(FONT_FAMILY, cairo::FontSlant::Normal, cairo::FontWeight::Normal);
+    cr.set_font_size(FONT_SIZE);
+    cr.move_to(0.0, TITLE_SIZE + LINE_HEIGHT + 4.0);
+    cr.show_text(&ellipsize(cr, &report.date_range, width)?)?;
+    cr.move_to(0.0, TITLE_SIZE + LINE_HEIGHT * 2.0 + 4.0);
+    cr.show_text(&ellipsize(cr, &report.filter, width)?)?;
+
+    Ok(HEADER_HEIGHT - LINE_HEIGHT)
+}
+
+fn draw_row(cr: &cairo::Context,
+            width: f64,
+            y: f64,
+            indent: f64,
+            label: &str,
+            duration: &str,
+            weight: cairo::FontWeight) -> Result<(), cairo::Error> {
+    // Label on the left and its duration right-aligned
+    cr.select_font_face(FONT_FAMILY, cairo::FontSlant::Normal, weight);
+    cr.set_font_size(FONT_SIZE);
+
+    let duration_width = cr.text_extents(duration)?.x_advance;
+    cr.move_to(width - duration_width, y);
+    cr.show_text(duration)?;
+
+    let label_width = width - indent - duration_width - DURATION_GAP;
+    cr.move_to(indent, y);
+    cr.show_text(&ellipsize(cr, label, label_width)?)?;
+    Ok(())
+}
+
+fn ellipsize(cr: &cairo::Context, text: &str, max_width: f64) -> Result<String, cairo::Error> {
+    // Shorten text that doesn't fit with the current font
+    if cr.text_extents(text)?.x_advance <= max_width {
+        return Ok(text.to_string());
+    }
+    let mut chars: Vec<char> = text.chars().collect();
+    while !chars.is_empty() {
+        chars.pop();
+        let shortened = format!("{}…", chars.iter().collect::<String>().trim_end());
+        if cr.text_extents(&shortened)?.x_advance <= max_width {
+            return Ok(shortened);
+        }
+    }
+    Ok(String::new())
+}
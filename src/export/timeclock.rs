@@ -0,0 +1,75 @@
+// Furtherance - Track your time without being tracked
+// Copyright (C) 2022  Ricky Kresslein <rk@lakoliu.com>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use itertools::Itertools;
+use std::collections::HashMap;
+
+use crate::database::{Pause, Task};
+use crate::export::worked_intervals;
+use crate::quick_entry;
+
+const TIME_FORMAT: &str = "%Y/%m/%d %H:%M:%S";
+
+pub fn to_timeclock(tasks: &[(Task, i64)], pauses: &HashMap<i32, Vec<Pause>>, account_map: &str) -> String {
+    // Write each task as i/o pairs that hledger and ledger can read, one for every stretch between pauses
+    let account_map = parse_account_map(account_map);
+    let mut tasks: Vec<&Task> = tasks.iter().map(|(task, _)| task).collect();
+    tasks.sort_by(|a, b| a.start_time.cmp(&b.start_time));
+
+    let mut timeclock = String::new();
+    for task in tasks {
+        let tags = quick_entry::split_tags(&task.tags);
+
+        // The first tag with an account decides where the task is booked
+        let task_name = account_name(&task.task_name);
+        let account = match tags.iter().find_map(|tag| account_map.iter().find(|(t, _)| t == tag)) {
+            Some((_, account)) => format!("{}:{}", account, task_name),
+            None => task_name,
+        };
+
+        for (start, stop) in worked_intervals(task, pauses) {
+            timeclock.push_str(&format!("i {} {}", start.format(TIME_FORMAT), account));
+            if !tags.is_empty() {
+                timeclock.push_str(&format!("  #{}", tags.join(" #")));
+            }
+            timeclock.push('\n');
+            timeclock.push_str(&format!("o {}\n", stop.format(TIME_FORMAT)));
+        }
+    }
+
+    timeclock
+}
+
+fn parse_account_map(account_map: &str) -> Vec<(String, String)> {
+    // Mappings look like "tag=account:name, other tag=account"
+    account_map.split(',')
+        .filter_map(|mapping| {
+            let (tag, account) = mapping.split_once('=')?;
+            let tag = tag.trim().trim_start_matches('#').to_lowercase();
+            let account = account_name(account);
+            if tag.is_empty() || account.is_empty() {
+                None
+            } else {
+                Some((tag, account))
+            }
+        })
+        .collect()
+}
+
+fn account_name(name: &str) -> String {
+    // Two spaces end an account name, so collapse any runs of whitespace
+    name.split_whitespace().join(" ")
+}
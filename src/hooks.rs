@@ -0,0 +1,95 @@
+// Furtherance - Track your time without being tracked
+// Copyright (C) 2022  Ricky Kresslein <rk@lakoliu.com>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use chrono::{DateTime, Local};
+use directories::ProjectDirs;
+use log::debug;
+use serde_json::{json, Value};
+use std::io::Write;
+use std::os::unix::fs::PermissionsExt;
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+use std::thread;
+
+use crate::quick_entry;
+
+pub enum Hook {
+    Start,
+    Stop,
+    Idle,
+    PomodoroEnd,
+}
+
+impl Hook {
+    fn file_name(&self) -> &'static str {
+        match self {
+            Hook::Start => "on-start",
+            Hook::Stop => "on-stop",
+            Hook::Idle => "on-idle",
+            Hook::PomodoroEnd => "on-pomodoro-end",
+        }
+    }
+}
+
+fn get_hooks_directory() -> Option<PathBuf> {
+    // ~/.config/furtherance/hooks on Linux, the Flatpak has its own config
+    // in ~/.var/app/com.lakoliu.Furtherance/config and runs hooks in its sandbox
+    let proj_dirs = ProjectDirs::from("com", "lakoliu", "Furtherance")?;
+    Some(proj_dirs.config_dir().join("hooks"))
+}
+
+pub fn entry(task_text: &str, start_time: DateTime<Local>, stop_time: Option<DateTime<Local>>) -> Value {
+    // The task as hooks get it on stdin, from the task input
+    let (task_name, tag_list) = quick_entry::split_task_text(task_text);
+    let tags = quick_entry::split_tags(&tag_list);
+    let stop = stop_time.unwrap_or_else(Local::now);
+    json!({
+        "task": task_name,
+        "tags": tags,
+        "start_time": start_time.to_rfc3339(),
+        "stop_time": stop_time.map(|x| x.to_rfc3339()),
+        "duration": (stop - start_time).num_seconds(),
+    })
+}
+
+pub fn run(hook: Hook, entry: Value) {
+    // Run the user's script for this event, if there is one, without waiting for it
+    let path = match get_hooks_directory() {
+        Some(dir) => dir.join(hook.file_name()),
+        None => return,
+    };
+    let is_executable = path.metadata()
+        .map(|metadata| metadata.is_file() && metadata.permissions().mode() & 0o111 != 0)
+        .unwrap_or(false);
+    if !is_executable {
+        return;
+    }
+
+    thread::spawn(move || {
+        let mut child = match Command::new(&path).stdin(Stdio::piped()).spawn() {
+            Ok(child) => child,
+            Err(err) => {
+                debug!("Could not run hook {}: {}", path.display(), err);
+                return;
+            }
+        };
+        if let Some(mut stdin) = child.stdin.take() {
+            // Scripts are free to ignore their input
+            let _ = writeln!(stdin, "{}", entry);
+        }
+        let _ = child.wait();
+    });
+}
@@ -0,0 +1,57 @@
+// Furtherance - Track your time without being tracked
+// Copyright (C) 2022  Ricky Kresslein <rk@lakoliu.com>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+mod mutter;
+mod screensaver;
+mod wayland;
+mod x11;
+
+use once_cell::sync::Lazy;
+use std::error::Error;
+use std::fmt;
+use std::sync::Mutex;
+
+// Something that knows how long the keyboard and mouse have been untouched
+pub trait IdleSource: fmt::Debug + Send {
+    fn name(&self) -> &'static str;
+    // Seconds since the user last did something
+    fn idle_time(&self) -> Result<u64, Box<dyn Error>>;
+}
+
+type NewSource = fn() -> Result<Box<dyn IdleSource>, Box<dyn Error>>;
+
+// Found the first time it is needed, then kept for as long as the app runs
+static SOURCE: Lazy<Mutex<Option<Box<dyn IdleSource>>>> = Lazy::new(|| Mutex::new(detect()));
+
+fn detect() -> Option<Box<dyn IdleSource>> {
+    // Use the first source that answers, desktop services before display protocols
+    let sources: Vec<NewSource> = vec![
+        || Ok(Box::new(mutter::MutterIdleMonitor::new()?)),
+        || Ok(Box::new(screensaver::ScreenSaver::new()?)),
+        || Ok(Box::new(wayland::ExtIdleNotify::new()?)),
+        || Ok(Box::new(x11::XScreenSaver::new()?)),
+    ];
+    sources.into_iter()
+        .filter_map(|source| source().ok())
+        .find(|source| source.idle_time().is_ok())
+}
+
+pub fn idle_time() -> Option<u64> {
+    SOURCE.lock().unwrap().as_ref()?.idle_time().ok()
+}
+
+pub fn source_name() -> Option<&'static str> {
+    SOURCE.lock().unwrap().as_ref().map(|source| source.name())
+}
@@ -0,0 +1,58 @@
+// Furtherance - Track your time without being tracked
+// Copyright (C) 2022  Ricky Kresslein <rk@lakoliu.com>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+use dbus::blocking::Connection;
+use std::error::Error;
+use std::fmt;
+use std::time::Duration;
+
+use crate::idle::IdleSource;
+
+// GNOME Shell
+pub struct MutterIdleMonitor {
+    connection: Connection,
+}
+
+impl MutterIdleMonitor {
+    pub fn new() -> Result<Self, Box<dyn Error>> {
+        Ok(MutterIdleMonitor::with_connection(Connection::new_session()?))
+    }
+
+    pub fn with_connection(connection: Connection) -> Self {
+        MutterIdleMonitor { connection }
+    }
+}
+
+impl fmt::Debug for MutterIdleMonitor {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("MutterIdleMonitor")
+    }
+}
+
+impl IdleSource for MutterIdleMonitor {
+    fn name(&self) -> &'static str {
+        "GNOME"
+    }
+
+    fn idle_time(&self) -> Result<u64, Box<dyn Error>> {
+        let proxy = self.connection.with_proxy("org.gnome.Mutter.IdleMonitor",
+            "/org/gnome/Mutter/IdleMonitor/Core",
+            Duration::from_millis(5000)
+        );
+        let (idle_time,): (u64,) = proxy.method_call("org.gnome.Mutter.IdleMonitor", "GetIdletime", ())?;
+
+        Ok(idle_time / 1000)
+    }
+}
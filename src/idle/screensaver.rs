@@ -0,0 +1,108 @@
+// Furtherance - Track your time without being tracked
+// Copyright (C) 2022  Ricky Kresslein <rk@lakoliu.com>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+use dbus::blocking::Connection;
+use std::error::Error;
+use std::fmt;
+use std::time::Duration;
+
+use crate::idle::IdleSource;
+
+// KDE Plasma and other desktops with the freedesktop.org screen saver service.
+// GNOME has the service too, but answers GetSessionIdleTime with an error.
+pub struct ScreenSaver {
+    connection: Connection,
+}
+
+impl ScreenSaver {
+    pub fn new() -> Result<Self, Box<dyn Error>> {
+        Ok(ScreenSaver::with_connection(Connection::new_session()?))
+    }
+
+    pub fn with_connection(connection: Connection) -> Self {
+        ScreenSaver { connection }
+    }
+}
+
+impl fmt::Debug for ScreenSaver {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("ScreenSaver")
+    }
+}
+
+impl IdleSource for ScreenSaver {
+    fn name(&self) -> &'static str {
+        "freedesktop.org ScreenSaver"
+    }
+
+    fn idle_time(&self) -> Result<u64, Box<dyn Error>> {
+        let proxy = self.connection.with_proxy("org.freedesktop.ScreenSaver",
+            "/org/freedesktop/ScreenSaver",
+            Duration::from_millis(5000)
+        );
+        let (idle_time,): (u32,) = proxy.method_call("org.freedesktop.ScreenSaver", "GetSessionIdleTime", ())?;
+
+        Ok(idle_time as u64 / 1000)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_bus::PrivateBus;
+    use dbus::channel::{MatchingReceiver, Sender};
+    use dbus::message::MatchRule;
+    use std::sync::mpsc;
+    use std::thread;
+
+    fn serve_idle_time(bus: &PrivateBus, idle_time: u32) {
+        // Answers GetSessionIdleTime until the bus goes away
+        let service = bus.connect();
+        let (ready, wait) = mpsc::channel();
+        thread::spawn(move || {
+            service.request_name("org.freedesktop.ScreenSaver", false, false, true).unwrap();
+            service.start_receive(MatchRule::new_method_call(), Box::new(move |call, service| {
+                let _ = service.send(call.method_return().append1(idle_time));
+                true
+            }));
+            ready.send(()).unwrap();
+            while service.process(Duration::from_millis(100)).is_ok() {}
+        });
+        wait.recv().unwrap();
+    }
+
+    #[test]
+    fn idle_time_in_seconds() {
+        let bus = match PrivateBus::start() {
+            Some(bus) => bus,
+            None => return eprintln!("dbus-daemon is not installed, skipping"),
+        };
+        serve_idle_time(&bus, 90_500);
+
+        let screen_saver = ScreenSaver::with_connection(bus.connect());
+        assert_eq!(screen_saver.idle_time().unwrap(), 90);
+    }
+
+    #[test]
+    fn no_screen_saver_is_an_error() {
+        let bus = match PrivateBus::start() {
+            Some(bus) => bus,
+            None => return eprintln!("dbus-daemon is not installed, skipping"),
+        };
+
+        let screen_saver = ScreenSaver::with_connection(bus.connect());
+        assert!(screen_saver.idle_time().is_err());
+    }
+}
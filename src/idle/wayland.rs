@@ -0,0 +1,114 @@
+// Furtherance - Track your time without being tracked
+// Copyright (C) 2022  Ricky Kresslein <rk@lakoliu.com>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+use std::error::Error;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+use wayland_client::globals::{registry_queue_init, GlobalListContents};
+use wayland_client::protocol::{wl_registry, wl_seat};
+use wayland_client::{delegate_noop, Connection, Dispatch, QueueHandle};
+use wayland_protocols::ext::idle_notify::v1::client::{ext_idle_notification_v1, ext_idle_notifier_v1};
+
+use crate::idle::IdleSource;
+
+const TIMEOUT_MS: u32 = 1000;
+
+#[derive(Debug)]
+pub struct ExtIdleNotify {
+    // None while the user is active, or if the connection was lost
+    idle_since: Arc<Mutex<Option<Instant>>>,
+    connected: Arc<Mutex<bool>>,
+}
+
+impl ExtIdleNotify {
+    pub fn new() -> Result<Self, Box<dyn Error>> {
+        let connection = Connection::connect_to_env()?;
+        let (globals, mut queue) = registry_queue_init::<State>(&connection)?;
+        let handle = queue.handle();
+
+        let seat: wl_seat::WlSeat = globals.bind(&handle, 1..=1, ())?;
+        let idle_notifier: ext_idle_notifier_v1::ExtIdleNotifierV1 = globals.bind(&handle, 1..=1, ())
+            .map_err(|_| "The compositor doesn't support ext-idle-notify-v1")?;
+        idle_notifier.get_idle_notification(TIMEOUT_MS, &seat, &handle, ());
+
+        let mut state = State { idle_since: Arc::new(Mutex::new(None)) };
+        // Fails here if the compositor refused the notification
+        queue.roundtrip(&mut state)?;
+
+        let idle_since = state.idle_since.clone();
+        let connected = Arc::new(Mutex::new(true));
+        let thread_connected = connected.clone();
+        thread::spawn(move || {
+            while queue.blocking_dispatch(&mut state).is_ok() {}
+            *state.idle_since.lock().unwrap() = None;
+            *thread_connected.lock().unwrap() = false;
+        });
+
+        Ok(ExtIdleNotify { idle_since, connected })
+    }
+}
+
+impl IdleSource for ExtIdleNotify {
+    fn name(&self) -> &'static str {
+        "Wayland"
+    }
+
+    fn idle_time(&self) -> Result<u64, Box<dyn Error>> {
+        if !*self.connected.lock().unwrap() {
+            return Err("Lost the connection to the compositor".into());
+        }
+        match *self.idle_since.lock().unwrap() {
+            Some(idle_since) => {
+                let idle_time = idle_since.elapsed() + Duration::from_millis(TIMEOUT_MS as u64);
+                Ok(idle_time.as_secs())
+            }
+            None => Ok(0),
+        }
+    }
+}
+
+// Lives on the thread that reads the compositor's events
+struct State {
+    idle_since: Arc<Mutex<Option<Instant>>>,
+}
+
+impl Dispatch<ext_idle_notification_v1::ExtIdleNotificationV1, ()> for State {
+    fn event(state: &mut Self,
+             _: &ext_idle_notification_v1::ExtIdleNotificationV1,
+             event: ext_idle_notification_v1::Event,
+             _: &(),
+             _: &Connection,
+             _: &QueueHandle<Self>) {
+        match event {
+            ext_idle_notification_v1::Event::Idled => *state.idle_since.lock().unwrap() = Some(Instant::now()),
+            ext_idle_notification_v1::Event::Resumed => *state.idle_since.lock().unwrap() = None,
+            _ => (),
+        }
+    }
+}
+
+impl Dispatch<wl_registry::WlRegistry, GlobalListContents> for State {
+    fn event(_: &mut Self,
+             _: &wl_registry::WlRegistry,
+             _: wl_registry::Event,
+             _: &GlobalListContents,
+             _: &Connection,
+             _: &QueueHandle<Self>) {
+    }
+}
+
+delegate_noop!(State: ignore wl_seat::WlSeat);
+delegate_noop!(State: ext_idle_notifier_v1::ExtIdleNotifierV1);
@@ -0,0 +1,54 @@
+// Furtherance - Track your time without being tracked
+// Copyright (C) 2022  Ricky Kresslein <rk@lakoliu.com>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+use std::error::Error;
+use x11rb::connection::{Connection, RequestConnection};
+use x11rb::protocol::screensaver::{self, ConnectionExt};
+use x11rb::protocol::xproto::Window;
+use x11rb::rust_connection::RustConnection;
+
+use crate::idle::IdleSource;
+
+#[derive(Debug)]
+pub struct XScreenSaver {
+    connection: RustConnection,
+    root: Window,
+}
+
+impl XScreenSaver {
+    pub fn new() -> Result<Self, Box<dyn Error>> {
+        // Under XWayland it would only see input to X11 windows
+        if std::env::var_os("WAYLAND_DISPLAY").is_some() {
+            return Err("X11 idle time is wrong in a Wayland session".into());
+        }
+        let (connection, screen) = x11rb::connect(None)?;
+        if connection.extension_information(screensaver::X11_EXTENSION_NAME)?.is_none() {
+            return Err("The X server doesn't have the MIT-SCREEN-SAVER extension".into());
+        }
+        let root = connection.setup().roots[screen].root;
+        Ok(XScreenSaver { connection, root })
+    }
+}
+
+impl IdleSource for XScreenSaver {
+    fn name(&self) -> &'static str {
+        "X11"
+    }
+
+    fn idle_time(&self) -> Result<u64, Box<dyn Error>> {
+        let info = self.connection.screensaver_query_info(self.root)?.reply()?;
+        Ok(info.ms_since_user_input as u64 / 1000)
+    }
+}
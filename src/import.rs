@@ -0,0 +1,95 @@
+// Furtherance - Track your time without being tracked
+// Copyright (C) 2022  Ricky Kresslein <rk@lakoliu.com>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+mod hamster;
+mod toggl;
+mod watson;
+
+use chrono::{DateTime, Local};
+use gettextrs::*;
+use itertools::Itertools;
+use std::error::Error;
+use std::path::Path;
+
+use crate::database::{self, Task};
+use crate::quick_entry;
+
+pub struct ImportOptions {
+    pub project_as_tag: bool,
+    pub resume_running: bool,
+}
+
+pub struct ImportSummary {
+    pub imported: usize,
+    pub duplicates: usize,
+    // Task input text and start time of a timer left running in the other tracker
+    pub running: Option<(String, DateTime<Local>)>,
+}
+
+pub fn import_file(path: &Path, options: &ImportOptions) -> Result<ImportSummary, Box<dyn Error>> {
+    // Figure out which tracker the file came from and read its entries
+    let mut running = None;
+    let tasks = if toggl::is_toggl_csv(path) {
+        toggl::read_tasks(path, options.project_as_tag)?
+    } else if hamster::is_hamster_db(path) {
+        hamster::read_tasks(path, options.project_as_tag)?
+    } else if watson::is_watson_frames(path) {
+        if options.resume_running {
+            running = watson::read_state(path);
+        }
+        watson::read_tasks(path)?
+    } else {
+        return Err(gettext("The file is not in a supported format.").into());
+    };
+
+    let total = tasks.len();
+    let imported = database::import_tasks(tasks)?;
+
+    Ok(ImportSummary {
+        imported,
+        duplicates: total - imported,
+        running,
+    })
+}
+
+fn new_task(task_name: &str,
+            project: &str,
+            start_time: DateTime<Local>,
+            stop_time: DateTime<Local>,
+            tags: Vec<String>,
+            description: &str) -> Task {
+    // Tags are stored the same way as tags typed into the task input, quoted if they have a #
+    let split_tags: Vec<String> = tags.iter()
+        .map(|x| x.trim().to_lowercase())
+        // Don't allow empty tags
+        .filter(|x| !x.is_empty())
+        // Handle duplicate tags before they are saved
+        .unique()
+        .collect();
+
+    Task {
+        id: 0,
+        task_name: task_name.trim().to_string(),
+        start_time: start_time.to_rfc3339(),
+        stop_time: stop_time.to_rfc3339(),
+        tags: quick_entry::join_tags(&split_tags),
+        description: description.trim().to_string(),
+        pomodoros: 0,
+        project: project.trim().to_string(),
+        billable: false,
+        paused: 0,
+    }
+}
@@ -0,0 +1,157 @@
+// Furtherance - Track your time without being tracked
+// Copyright (C) 2022  Ricky Kresslein <rk@lakoliu.com>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use chrono::{DateTime, Local, NaiveDateTime, offset::TimeZone};
+use rusqlite::{Connection, OpenFlags};
+use std::error::Error;
+use std::path::Path;
+
+use crate::database::Task;
+use crate::import::new_task;
+
+fn open(path: &Path) -> rusqlite::Result<Connection> {
+    // Never write to another app's database
+    Connection::open_with_flags(path, OpenFlags::SQLITE_OPEN_READ_ONLY)
+}
+
+pub fn is_hamster_db(path: &Path) -> bool {
+    let conn = match open(path) {
+        Ok(conn) => conn,
+        Err(_) => return false,
+    };
+    let tables: rusqlite::Result<i64> = conn.query_row(
+        "SELECT COUNT(*) FROM sqlite_master WHERE type = 'table'
+            AND name IN ('facts', 'activities', 'categories', 'tags', 'fact_tags')",
+        [],
+        |row| row.get(0),
+    );
+    matches!(tables, Ok(5))
+}
+
+pub fn read_tasks(path: &Path, category_as_tag: bool) -> Result<Vec<Task>, Box<dyn Error>> {
+    let conn = open(path)?;
+
+    // Facts that are still running have no end time and are left out
+    let mut facts_query = conn.prepare(
+        "SELECT facts.id, activities.name, categories.name,
+                facts.start_time, facts.end_time, facts.description
+            FROM facts
+            JOIN activities ON activities.id = facts.activity_id
+            LEFT JOIN categories ON categories.id = activities.category_id
+            WHERE facts.end_time IS NOT NULL
+            ORDER BY facts.start_time")?;
+    let mut tags_query = conn.prepare(
+        "SELECT tags.name FROM fact_tags
+            JOIN tags ON tags.id = fact_tags.tag_id
+            WHERE fact_tags.fact_id = (?1)")?;
+
+    let mut facts = facts_query.query([])?;
+    let mut tasks: Vec<Task> = Vec::new();
+    while let Some(fact) = facts.next()? {
+        let fact_id: i64 = fact.get(0)?;
+        let activity: String = fact.get(1)?;
+        let category: Option<String> = fact.get(2)?;
+        let start_time = parse_timestamp(&fact.get::<_, String>(3)?)?;
+        let stop_time = parse_timestamp(&fact.get::<_, String>(4)?)?;
+        let description: Option<String> = fact.get(5)?;
+
+        if activity.trim().is_empty() || stop_time < start_time {
+            continue;
+        }
+
+        // Hamster's categories are projects
+        let project = category.unwrap_or_default();
+        let mut tags: Vec<String> = Vec::new();
+        if category_as_tag {
+            tags.push(project.clone());
+        }
+        let fact_tags = tags_query.query_map([fact_id], |row| row.get(0))?;
+        for tag in fact_tags {
+            tags.push(tag?);
+        }
+
+        tasks.push(new_task(&activity,
+                            &project,
+                            start_time,
+                            stop_time,
+                            tags,
+                            &description.unwrap_or_default()));
+    }
+
+    Ok(tasks)
+}
+
+fn parse_timestamp(timestamp: &str) -> Result<DateTime<Local>, Box<dyn Error>> {
+    // Hamster stores naive local times, sometimes with fractional seconds
+    let naive = NaiveDateTime::parse_from_str(timestamp, "%Y-%m-%d %H:%M:%S%.f")?;
+    let local = Local.from_local_datetime(&naive)
+        .earliest()
+        .ok_or("Time does not exist in the local time zone")?;
+    Ok(local)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use std::path::PathBuf;
+
+    fn hamster_db(test: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("furtherance-hamster-{}-{}.db", std::process::id(), test));
+        let _ = fs::remove_file(&path);
+        let conn = Connection::open(&path).unwrap();
+        conn.execute_batch(
+            "CREATE TABLE categories (id INTEGER PRIMARY KEY, name TEXT);
+            CREATE TABLE activities (id INTEGER PRIMARY KEY, name TEXT, category_id INTEGER);
+            CREATE TABLE facts (id INTEGER PRIMARY KEY, activity_id INTEGER,
+                start_time TIMESTAMP, end_time TIMESTAMP, description TEXT);
+            CREATE TABLE tags (id INTEGER PRIMARY KEY, name TEXT);
+            CREATE TABLE fact_tags (fact_id INTEGER, tag_id INTEGER);
+            INSERT INTO categories VALUES (1, 'Work');
+            INSERT INTO activities VALUES (1, 'Email', 1), (2, 'Reading', NULL);
+            INSERT INTO tags VALUES (1, 'Inbox');
+            INSERT INTO facts VALUES
+                (1, 1, '2022-05-02 09:00:00', '2022-05-02 09:30:00.250000', 'Morning'),
+                (2, 2, '2022-05-02 10:00:00', NULL, NULL);
+            INSERT INTO fact_tags VALUES (1, 1);").unwrap();
+        path
+    }
+
+    #[test]
+    fn recognizes_hamster_db() {
+        let path = hamster_db("recognize");
+        assert!(is_hamster_db(&path));
+        assert!(!is_hamster_db(&path.with_extension("missing")));
+    }
+
+    #[test]
+    fn reads_facts() {
+        let path = hamster_db("facts");
+        let tasks = read_tasks(&path, true).unwrap();
+        assert_eq!(tasks[0].task_name, "Email");
+        assert_eq!(tasks[0].project, "Work");
+        assert_eq!(tasks[0].tags, "work #inbox");
+        assert_eq!(tasks[0].description, "Morning");
+    }
+
+    #[test]
+    fn facts_without_end_time_are_skipped() {
+        let path = hamster_db("running");
+        let tasks = read_tasks(&path, false).unwrap();
+        assert_eq!(tasks.len(), 1);
+        assert_eq!(tasks[0].task_name, "Email");
+    }
+}
@@ -0,0 +1,177 @@
+// Furtherance - Track your time without being tracked
+// Copyright (C) 2022  Ricky Kresslein <rk@lakoliu.com>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use chrono::{DateTime, Local, NaiveDateTime, offset::TimeZone};
+use std::error::Error;
+use std::path::Path;
+
+use crate::database::Task;
+use crate::import::new_task;
+
+const PROJECT: &str = "Project";
+const DESCRIPTION: &str = "Description";
+const TAGS: &str = "Tags";
+const START_DATE: &str = "Start date";
+const START_TIME: &str = "Start time";
+const END_DATE: &str = "End date";
+const END_TIME: &str = "End time";
+
+// Column indexes of the fields Furtherance uses in a detailed report
+struct Columns {
+    project: usize,
+    description: usize,
+    tags: usize,
+    start_date: usize,
+    start_time: usize,
+    end_date: usize,
+    end_time: usize,
+}
+
+impl Columns {
+    fn from_headers(headers: &csv::StringRecord) -> Option<Columns> {
+        // Toggl prefixes its exports with a byte order mark
+        let headers: Vec<&str> = headers.iter()
+            .map(|x| x.trim_start_matches('\u{feff}').trim())
+            .collect();
+        let position = |name: &str| headers.iter().position(|&x| x == name);
+
+        Some(Columns {
+            project: position(PROJECT)?,
+            description: position(DESCRIPTION)?,
+            tags: position(TAGS)?,
+            start_date: position(START_DATE)?,
+            start_time: position(START_TIME)?,
+            end_date: position(END_DATE)?,
+            end_time: position(END_TIME)?,
+        })
+    }
+}
+
+pub fn is_toggl_csv(path: &Path) -> bool {
+    let mut reader = match csv::ReaderBuilder::new().flexible(true).from_path(path) {
+        Ok(reader) => reader,
+        Err(_) => return false,
+    };
+    match reader.headers() {
+        Ok(headers) => Columns::from_headers(headers).is_some(),
+        Err(_) => false,
+    }
+}
+
+pub fn read_tasks(path: &Path, project_as_tag: bool) -> Result<Vec<Task>, Box<dyn Error>> {
+    let mut reader = csv::ReaderBuilder::new().flexible(true).from_path(path)?;
+    let columns = Columns::from_headers(reader.headers()?)
+        .ok_or("Missing Toggl Track columns")?;
+
+    let mut tasks: Vec<Task> = Vec::new();
+    for record in reader.records() {
+        let record = record?;
+        let field = |i: usize| record.get(i).unwrap_or("").trim();
+
+        let start_time = parse_date_time(field(columns.start_date), field(columns.start_time))?;
+        let stop_time = parse_date_time(field(columns.end_date), field(columns.end_time))?;
+        if stop_time < start_time {
+            continue;
+        }
+
+        // Entries without a description are named after their project
+        let project = field(columns.project);
+        let task_name = if field(columns.description).is_empty() {
+            project
+        } else {
+            field(columns.description)
+        };
+        if task_name.is_empty() {
+            continue;
+        }
+
+        let mut tags: Vec<String> = field(columns.tags).split(',').map(|x| x.to_string()).collect();
+        if project_as_tag {
+            tags.push(project.to_string());
+        }
+
+        tasks.push(new_task(task_name, project, start_time, stop_time, tags, ""));
+    }
+
+    Ok(tasks)
+}
+
+fn parse_date_time(date: &str, time: &str) -> Result<DateTime<Local>, Box<dyn Error>> {
+    let naive = NaiveDateTime::parse_from_str(&format!("{} {}", date, time), "%Y-%m-%d %H:%M:%S")?;
+    let local = Local.from_local_datetime(&naive)
+        .earliest()
+        .ok_or("Time does not exist in the local time zone")?;
+    Ok(local)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use std::path::PathBuf;
+
+    const HEADER: &str = "\u{feff}User,Email,Client,Project,Task,Description,Billable,\
+        Start date,Start time,End date,End time,Duration,Tags,Amount ()";
+
+    fn csv_file(test: &str, rows: &[&str]) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("furtherance-toggl-{}-{}.csv", std::process::id(), test));
+        fs::write(&path, format!("{}\n{}\n", HEADER, rows.join("\n"))).unwrap();
+        path
+    }
+
+    #[test]
+    fn header_with_byte_order_mark() {
+        let path = csv_file("bom", &[]);
+        assert!(is_toggl_csv(&path));
+    }
+
+    #[test]
+    fn reads_entries() {
+        let path = csv_file("entries", &[
+            "Ann,ann@example.com,,Website,,Write report,No,2022-05-02,09:00:00,2022-05-02,10:30:00,01:30:00,\"work, c#\",",
+        ]);
+
+        let tasks = read_tasks(&path, false).unwrap();
+        assert_eq!(tasks.len(), 1);
+        assert_eq!(tasks[0].task_name, "Write report");
+        assert_eq!(tasks[0].project, "Website");
+        assert_eq!(tasks[0].tags, r#"work #"c#""#);
+        assert_eq!(tasks[0].start_time, parse_date_time("2022-05-02", "09:00:00").unwrap().to_rfc3339());
+    }
+
+    #[test]
+    fn empty_description_uses_project() {
+        let path = csv_file("description", &[
+            "Ann,ann@example.com,,Website,,,No,2022-05-02,09:00:00,2022-05-02,10:30:00,01:30:00,,",
+        ]);
+
+        let tasks = read_tasks(&path, true).unwrap();
+        assert_eq!(tasks[0].task_name, "Website");
+        assert_eq!(tasks[0].tags, "website");
+    }
+
+    #[test]
+    fn end_before_start_is_skipped() {
+        let path = csv_file("backwards", &[
+            "Ann,ann@example.com,,Website,,Write report,No,2022-05-02,10:30:00,2022-05-02,09:00:00,00:00:00,,",
+            "Ann,ann@example.com,,Website,,Review,No,2022-05-02,11:00:00,2022-05-02,11:15:00,00:15:00,,",
+        ]);
+
+        let tasks = read_tasks(&path, false).unwrap();
+        assert_eq!(tasks.len(), 1);
+        assert_eq!(tasks[0].task_name, "Review");
+    }
+}
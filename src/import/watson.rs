@@ -0,0 +1,177 @@
+// Furtherance - Track your time without being tracked
+// Copyright (C) 2022  Ricky Kresslein <rk@lakoliu.com>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use chrono::{DateTime, Local, offset::TimeZone};
+use serde_json::Value;
+use std::error::Error;
+use std::fs;
+use std::path::Path;
+
+use crate::database::Task;
+use crate::import::new_task;
+use crate::quick_entry;
+
+// Each frame is [start, stop, project, id, tags, updated_at]
+const START: usize = 0;
+const STOP: usize = 1;
+const PROJECT: usize = 2;
+const TAGS: usize = 4;
+
+fn read_json(path: &Path) -> Option<Value> {
+    let contents = fs::read_to_string(path).ok()?;
+    serde_json::from_str(&contents).ok()
+}
+
+fn is_frame(frame: &Value) -> bool {
+    match frame.as_array() {
+        Some(fields) => fields.len() > TAGS
+            && fields[START].is_number()
+            && fields[STOP].is_number()
+            && fields[PROJECT].is_string(),
+        None => false,
+    }
+}
+
+pub fn is_watson_frames(path: &Path) -> bool {
+    match read_json(path) {
+        // Any empty array would match otherwise
+        Some(Value::Array(frames)) => !frames.is_empty() && frames.iter().all(is_frame),
+        _ => false,
+    }
+}
+
+pub fn read_tasks(path: &Path) -> Result<Vec<Task>, Box<dyn Error>> {
+    let frames = match read_json(path) {
+        Some(Value::Array(frames)) => frames,
+        _ => return Err("Not a Watson frames file".into()),
+    };
+
+    let mut tasks: Vec<Task> = Vec::new();
+    for frame in frames.iter().filter(|frame| is_frame(frame)) {
+        let start_time = from_timestamp(&frame[START])?;
+        let stop_time = from_timestamp(&frame[STOP])?;
+        let project = frame[PROJECT].as_str().unwrap_or("");
+        if project.trim().is_empty() || stop_time < start_time {
+            continue;
+        }
+
+        tasks.push(new_task(project, project, start_time, stop_time, read_tags(&frame[TAGS]), ""));
+    }
+
+    Ok(tasks)
+}
+
+pub fn read_state(frames_path: &Path) -> Option<(String, DateTime<Local>)> {
+    // Watson keeps the running frame in a "state" file next to "frames"
+    let state = read_json(&frames_path.with_file_name("state"))?;
+    let project = state.get("project")?.as_str()?.trim();
+    if project.is_empty() {
+        return None;
+    }
+    let start_time = from_timestamp(state.get("start")?).ok()?;
+
+    let mut task_text = project.to_string();
+    let tags = read_tags(state.get("tags").unwrap_or(&Value::Null));
+    if !tags.is_empty() {
+        task_text = format!("{} #{}", task_text, quick_entry::join_tags(&tags));
+    }
+
+    Some((task_text, start_time))
+}
+
+fn read_tags(tags: &Value) -> Vec<String> {
+    match tags.as_array() {
+        Some(tags) => tags.iter()
+            .filter_map(|tag| tag.as_str())
+            .map(|tag| tag.to_string())
+            .collect(),
+        None => Vec::new(),
+    }
+}
+
+fn from_timestamp(timestamp: &Value) -> Result<DateTime<Local>, Box<dyn Error>> {
+    let secs = timestamp.as_f64().ok_or("Timestamp is not a number")?;
+    let local = Local.timestamp_opt(secs as i64, 0)
+        .single()
+        .ok_or("Timestamp is out of range")?;
+    Ok(local)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    fn frames_file(test: &str, frames: &str) -> PathBuf {
+        // Watson's files are always named "frames" and "state"
+        let dir = std::env::temp_dir().join(format!("furtherance-watson-{}-{}", std::process::id(), test));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("frames");
+        fs::write(&path, frames).unwrap();
+        path
+    }
+
+    #[test]
+    fn empty_array_is_not_watson() {
+        let path = frames_file("empty", "[]");
+        assert!(!is_watson_frames(&path));
+    }
+
+    #[test]
+    fn reads_frames() {
+        let path = frames_file("frames", r#"[
+            [1651482000, 1651485600, "write", "a1b2", ["work"], 1651485600],
+            [1651489200, 1651486000, "backwards", "c3d4", [], 1651489200]
+        ]"#);
+        assert!(is_watson_frames(&path));
+
+        let tasks = read_tasks(&path).unwrap();
+        assert_eq!(tasks.len(), 1);
+        assert_eq!(tasks[0].task_name, "write");
+        assert_eq!(tasks[0].project, "write");
+        assert_eq!(tasks[0].tags, "work");
+        assert_eq!(tasks[0].start_time, Local.timestamp(1651482000, 0).to_rfc3339());
+    }
+
+    #[test]
+    fn tags_are_normalized() {
+        let path = frames_file("tags", r#"[
+            [1651482000, 1651485600, "write", "a1b2", [" Work ", "work", "", "C#"], 1651485600]
+        ]"#);
+
+        let tasks = read_tasks(&path).unwrap();
+        assert_eq!(tasks[0].tags, r#"work #"c#""#);
+    }
+
+    #[test]
+    fn running_state() {
+        let path = frames_file("state", "[]");
+        fs::write(path.with_file_name("state"), r#"{"project": "write", "start": 1651482000, "tags": ["work", "c#"]}"#)
+            .unwrap();
+
+        let (task_text, start_time) = read_state(&path).unwrap();
+        assert_eq!(task_text, r#"write #work #"c#""#);
+        assert_eq!(start_time, Local.timestamp(1651482000, 0));
+    }
+
+    #[test]
+    fn nothing_running() {
+        let path = frames_file("stopped", "[]");
+        fs::write(path.with_file_name("state"), "{}").unwrap();
+
+        assert_eq!(read_state(&path), None);
+    }
+}
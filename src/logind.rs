@@ -0,0 +1,175 @@
+// Furtherance - Track your time without being tracked
+// Copyright (C) 2022  Ricky Kresslein <rk@lakoliu.com>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+use chrono::Local;
+use gettextrs::*;
+use gtk::prelude::*;
+use gtk::{gio, glib};
+use log::debug;
+use std::collections::HashMap;
+use std::os::unix::io::{FromRawFd, OwnedFd};
+use std::sync::Mutex;
+
+use crate::FurtheranceApplication;
+use crate::ui::FurtheranceWindow;
+
+const LOGIND_NAME: &str = "org.freedesktop.login1";
+const MANAGER_PATH: &str = "/org/freedesktop/login1";
+const MANAGER_INTERFACE: &str = "org.freedesktop.login1.Manager";
+const SESSION_INTERFACE: &str = "org.freedesktop.login1.Session";
+
+// Holding this makes logind wait a little before suspending
+static SLEEP_INHIBITOR: Mutex<Option<OwnedFd>> = Mutex::new(None);
+
+pub fn watch(app: &FurtheranceApplication) {
+    // Notice suspend and screen locks, which idle polling gets wrong
+    let connection = match gio::bus_get_sync(gio::BusType::System, None::<&gio::Cancellable>) {
+        Ok(connection) => connection,
+        Err(err) => {
+            debug!("No system bus, can't watch for suspend: {:?}", err);
+            return;
+        }
+    };
+
+    take_sleep_inhibitor(&connection);
+    connection.signal_subscribe(
+        Some(LOGIND_NAME),
+        Some(MANAGER_INTERFACE),
+        Some("PrepareForSleep"),
+        Some(MANAGER_PATH),
+        None,
+        gio::DBusSignalFlags::NONE,
+        clone_app(app, |app, connection, parameters| {
+            if let Some((true,)) = parameters.get::<(bool,)>() {
+                if let Some(window) = timer_window(app) {
+                    window.system_went_away(Local::now());
+                }
+                // Everything is saved, sleep can go ahead
+                SLEEP_INHIBITOR.lock().unwrap().take();
+            } else {
+                take_sleep_inhibitor(connection);
+                if let Some(window) = timer_window(app) {
+                    window.system_came_back();
+                }
+            }
+        }),
+    );
+
+    let session_path = match session_path(&connection) {
+        Some(session_path) => session_path,
+        None => return,
+    };
+    for (member, locked) in [("Lock", true), ("Unlock", false)] {
+        connection.signal_subscribe(
+            Some(LOGIND_NAME),
+            Some(SESSION_INTERFACE),
+            Some(member),
+            Some(&session_path),
+            None,
+            gio::DBusSignalFlags::NONE,
+            clone_app(app, move |app, _, _| screen_locked(app, locked)),
+        );
+    }
+    // Lock screens that don't go through logind still set LockedHint
+    connection.signal_subscribe(
+        Some(LOGIND_NAME),
+        Some("org.freedesktop.DBus.Properties"),
+        Some("PropertiesChanged"),
+        Some(&session_path),
+        Some(SESSION_INTERFACE),
+        gio::DBusSignalFlags::NONE,
+        clone_app(app, |app, _, parameters| {
+            let changed = parameters.child_value(1).get::<HashMap<String, glib::Variant>>().unwrap_or_default();
+            if let Some(locked) = changed.get("LockedHint").and_then(|x| x.get::<bool>()) {
+                screen_locked(app, locked);
+            }
+        }),
+    );
+}
+
+fn clone_app<F>(app: &FurtheranceApplication, callback: F)
+    -> impl Fn(&gio::DBusConnection, &str, &str, &str, &str, &glib::Variant) + 'static
+where
+    F: Fn(&FurtheranceApplication, &gio::DBusConnection, &glib::Variant) + 'static,
+{
+    // Signal handlers only need the app and the signal's parameters
+    let app = app.downgrade();
+    move |connection, _, _, _, _, parameters| {
+        if let Some(app) = app.upgrade() {
+            callback(&app, connection, parameters);
+        }
+    }
+}
+
+fn timer_window(app: &FurtheranceApplication) -> Option<FurtheranceWindow> {
+    app.windows()
+        .into_iter()
+        .find_map(|window| window.downcast::<FurtheranceWindow>().ok())
+}
+
+fn screen_locked(app: &FurtheranceApplication, locked: bool) {
+    if let Some(window) = timer_window(app) {
+        if locked {
+            window.system_went_away(Local::now());
+        } else {
+            window.system_came_back();
+        }
+    }
+}
+
+fn session_path(connection: &gio::DBusConnection) -> Option<String> {
+    // The session this app runs in
+    let reply = connection.call_sync(
+        Some(LOGIND_NAME),
+        MANAGER_PATH,
+        MANAGER_INTERFACE,
+        "GetSession",
+        Some(&("auto",).to_variant()),
+        Some(glib::VariantTy::new("(o)").unwrap()),
+        gio::DBusCallFlags::NONE,
+        1000,
+        None::<&gio::Cancellable>,
+    );
+    match reply {
+        Ok(reply) => reply.child_value(0).str().map(|x| x.to_string()),
+        Err(err) => {
+            debug!("Unable to find the login session: {:?}", err);
+            None
+        }
+    }
+}
+
+fn take_sleep_inhibitor(connection: &gio::DBusConnection) {
+    // A delay lock gives the autosave time to be written before suspending
+    let reply = connection.call_with_unix_fd_list_sync(
+        Some(LOGIND_NAME),
+        MANAGER_PATH,
+        MANAGER_INTERFACE,
+        "Inhibit",
+        Some(&("sleep", "Furtherance", &gettext("Saving the running task"), "delay").to_variant()),
+        Some(glib::VariantTy::new("(h)").unwrap()),
+        gio::DBusCallFlags::NONE,
+        1000,
+        None::<&gio::UnixFDList>,
+        None::<&gio::Cancellable>,
+    );
+    match reply {
+        Ok((_, fd_list)) => {
+            let inhibitor = fd_list.steal_fds().into_iter().next();
+            *SLEEP_INHIBITOR.lock().unwrap() = inhibitor.map(|fd| unsafe { OwnedFd::from_raw_fd(fd) });
+        }
+        Err(err) => debug!("Unable to delay suspend: {:?}", err),
+    }
+}
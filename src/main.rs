@@ -15,10 +15,24 @@
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
 mod application;
+mod autosave;
+mod cli;
 mod config;
 mod ui;
 mod database;
+mod dbus_service;
+mod export;
+mod hooks;
+mod idle;
+mod import;
+mod logind;
+mod quick_entry;
+mod search_provider;
 mod settings_manager;
+#[cfg(test)]
+mod test_bus;
+mod timer;
+mod utils;
 
 use self::application::FurtheranceApplication;
 
@@ -28,17 +42,23 @@ use gtk::{gio, glib};
 use gtk::prelude::*;
 
 fn main() {
-    // Initialize GTK
-    gtk::init().expect("Failed to initialize GTK.");
-    // Initialize libadwaita
-    adw::init();
-
     // Set up gettext translations
     bindtextdomain(GETTEXT_PACKAGE, LOCALEDIR).expect("Unable to bind the text domain");
     bind_textdomain_codeset(GETTEXT_PACKAGE, "UTF-8")
         .expect("Unable to set the text domain encoding");
     textdomain(GETTEXT_PACKAGE).expect("Unable to switch to the text domain");
 
+    // Commands like `furtherance start` run without a display
+    let args: Vec<String> = std::env::args().collect();
+    if let Some(exit_code) = cli::run(&args) {
+        std::process::exit(exit_code);
+    }
+
+    // Initialize GTK
+    gtk::init().expect("Failed to initialize GTK.");
+    // Initialize libadwaita
+    adw::init();
+
     // Load resources
     let resources = gio::Resource::load(PKGDATADIR.to_owned() + "/furtherance.gresource")
         .expect("Could not load resources");
@@ -47,7 +67,7 @@ fn main() {
     // Create a new GtkApplication. The application manages our main loop,
     // application windows, integration with the window manager/compositor, and
     // desktop features such as file opening and single-instance applications.
-    let app = FurtheranceApplication::new(APP_ID, &gio::ApplicationFlags::empty());
+    let app = FurtheranceApplication::new(APP_ID, &gio::ApplicationFlags::HANDLES_COMMAND_LINE);
 
     glib::set_application_name("Furtherance");
 
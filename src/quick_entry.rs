@@ -0,0 +1,433 @@
+// Furtherance - Track your time without being tracked
+// Copyright (C) 2022  Ricky Kresslein <rk@lakoliu.com>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use chrono::{DateTime, Duration, Local};
+use gettextrs::*;
+use itertools::Itertools;
+use std::error::Error;
+use std::fmt;
+
+use crate::database::Task;
+use crate::utils;
+
+// Start offsets and logged lengths can't be longer than this
+const MAX_HOURS: i64 = 24;
+
+// Everything that can be typed into the task input, like
+// "Write report #docs #"c# code" @acme $ -15m"
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct QuickEntry {
+    pub task_name: String,
+    pub tags: Vec<String>,
+    pub project: Option<String>,
+    pub billable: bool,
+    // How long ago the task started, from +10m or -10m
+    pub start_offset: Option<Duration>,
+    // Length of a finished entry that ends now, from ~1h30
+    pub logged: Option<Duration>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ParseError {
+    EmptyName,
+    EmptyTag,
+    UnclosedQuote,
+    TwoProjects,
+    BadDuration(String),
+    TooLong(String),
+    TwoTimes,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let message = match self {
+            ParseError::EmptyName => gettext("Task name cannot be empty."),
+            ParseError::EmptyTag => gettext("Quoted tags cannot be empty."),
+            ParseError::UnclosedQuote => gettext("A quoted tag is missing its closing quote."),
+            ParseError::TwoProjects => gettext("A task can only have one @project."),
+            ParseError::BadDuration(word) => {
+                format!("{} {}", word, gettext("is not a length of time. Use something like 15m, 2h or 1h30."))
+            }
+            ParseError::TooLong(word) => {
+                format!("{} {}", word, gettext("is longer than a day."))
+            }
+            ParseError::TwoTimes => gettext("Use only one start offset or ~length."),
+        };
+        write!(f, "{}", message)
+    }
+}
+
+impl Error for ParseError {}
+
+impl QuickEntry {
+    pub fn tag_list(&self) -> String {
+        join_tags(&self.tags)
+    }
+
+    pub fn task_text(&self) -> String {
+        // The entry the way it would be typed in, without its times
+        let mut text = utils::task_text(&self.task_name, &self.tag_list());
+        if let Some(project) = &self.project {
+            text.push_str(&format!(" @{}", project));
+        }
+        if self.billable {
+            text.push_str(" $");
+        }
+        text
+    }
+
+    pub fn to_task(&self, start_time: DateTime<Local>, stop_time: DateTime<Local>) -> Task {
+        // A finished entry, ready to be saved
+        Task {
+            id: 0,
+            task_name: self.task_name.clone(),
+            start_time: start_time.to_rfc3339(),
+            stop_time: stop_time.to_rfc3339(),
+            tags: self.tag_list(),
+            description: String::new(),
+            pomodoros: 0,
+            project: self.project.clone().unwrap_or_default(),
+            billable: self.billable,
+            paused: 0,
+        }
+    }
+}
+
+pub fn task_text(task: &Task) -> String {
+    // A saved task the way it would be typed in, to start it again
+    QuickEntry {
+        task_name: task.task_name.clone(),
+        tags: split_tags(&task.tags),
+        project: Some(task.project.clone()).filter(|x| !x.is_empty()),
+        billable: task.billable,
+        ..QuickEntry::default()
+    }.task_text()
+}
+
+enum Word {
+    Plain(String),
+    QuotedTag(String),
+}
+
+pub fn parse(input: &str) -> Result<QuickEntry, ParseError> {
+    let mut entry = QuickEntry::default();
+    let (task_name, tags) = split_words(input, |word| read_marker(word, &mut entry))?;
+    if task_name.is_empty() {
+        return Err(ParseError::EmptyName);
+    }
+    entry.task_name = task_name;
+    entry.tags = tags;
+    Ok(entry)
+}
+
+pub fn split_task_text(text: &str) -> (String, String) {
+    // The task name and tags of text from the task input, without its other markers
+    match parse(text) {
+        Ok(entry) => (entry.task_name.clone(), entry.tag_list()),
+        Err(_) => utils::split_tags_and_task(text),
+    }
+}
+
+pub fn split_tags(tag_list: &str) -> Vec<String> {
+    // Tags the way they are saved, "work #"c# code" #docs"
+    match split_words(&format!("#{}", tag_list), |_| Ok(false)) {
+        Ok((_, tags)) => tags,
+        Err(_) => tag_list.split('#')
+            .map(|x| x.trim().to_string())
+            .filter(|x| !x.is_empty())
+            .collect(),
+    }
+}
+
+pub fn join_tags(tags: &[String]) -> String {
+    // Tags with a # in them are quoted so they can be split again
+    tags.iter()
+        .map(|tag| if tag.contains('#') { format!("\"{}\"", tag) } else { tag.to_string() })
+        .join(" #")
+}
+
+fn split_words<F>(input: &str, mut read_marker: F) -> Result<(String, Vec<String>), ParseError>
+where
+    F: FnMut(&str) -> Result<bool, ParseError>,
+{
+    let mut name_words: Vec<String> = Vec::new();
+    let mut tags: Vec<String> = Vec::new();
+    // Plain words after a # belong to that tag, like "#other tag"
+    let mut open_tag = false;
+    for word in read_words(input)? {
+        let word = match word {
+            Word::QuotedTag(tag) => {
+                if tag.is_empty() {
+                    return Err(ParseError::EmptyTag);
+                }
+                tags.push(tag);
+                open_tag = false;
+                continue;
+            }
+            Word::Plain(word) => word,
+        };
+        if !word.contains('#') && read_marker(&word)? {
+            continue;
+        }
+
+        let mut parts = word.split('#');
+        let first = parts.next().unwrap_or_default();
+        if !first.is_empty() {
+            match tags.last_mut() {
+                Some(tag) if open_tag => {
+                    tag.push(' ');
+                    tag.push_str(first);
+                }
+                _ => name_words.push(first.to_string()),
+            }
+        }
+        for part in parts {
+            tags.push(part.to_string());
+            open_tag = true;
+        }
+    }
+
+    // Empty tags are left out and duplicates are only saved once
+    let tags = tags.iter()
+        .map(|x| x.trim().to_lowercase())
+        .filter(|x| !x.is_empty())
+        .unique()
+        .collect();
+    Ok((name_words.join(" "), tags))
+}
+
+fn read_words(input: &str) -> Result<Vec<Word>, ParseError> {
+    // Split on whitespace, except inside #"quoted tags"
+    let mut words = Vec::new();
+    let mut rest = input.trim_start();
+    while !rest.is_empty() {
+        if let Some(quoted) = rest.strip_prefix("#\"") {
+            let end = quoted.find('"').ok_or(ParseError::UnclosedQuote)?;
+            words.push(Word::QuotedTag(quoted[..end].trim().to_string()));
+            rest = &quoted[end + 1..];
+        } else {
+            let end = rest.find(char::is_whitespace).unwrap_or(rest.len());
+            words.push(Word::Plain(rest[..end].to_string()));
+            rest = &rest[end..];
+        }
+        rest = rest.trim_start();
+    }
+    Ok(words)
+}
+
+fn read_marker(word: &str, entry: &mut QuickEntry) -> Result<bool, ParseError> {
+    // Words that say something about the entry instead of naming it
+    if word == "$" {
+        entry.billable = true;
+        return Ok(true);
+    }
+    if let Some(project) = word.strip_prefix('@').filter(|x| !x.is_empty()) {
+        if entry.project.is_some() {
+            return Err(ParseError::TwoProjects);
+        }
+        entry.project = Some(project.to_string());
+        return Ok(true);
+    }
+
+    let mut chars = word.chars();
+    let sign = chars.next();
+    let length = chars.as_str();
+    // Anything else is part of the name, like "-" or "+44", but ~ is only used for times
+    let looks_like_time = match sign {
+        Some('~') => length.starts_with(|c: char| c.is_ascii_digit()),
+        Some('+' | '-') => {
+            length.starts_with(|c: char| c.is_ascii_digit())
+                && length.contains(['h', 'm'])
+                && length.chars().all(|c| c.is_ascii_digit() || c == 'h' || c == 'm')
+        }
+        _ => false,
+    };
+    if !looks_like_time {
+        return Ok(false);
+    }
+    let length = parse_length(length).ok_or_else(|| ParseError::BadDuration(word.to_string()))?;
+    if length > Duration::hours(MAX_HOURS) {
+        return Err(ParseError::TooLong(word.to_string()));
+    }
+    if entry.start_offset.is_some() || entry.logged.is_some() {
+        return Err(ParseError::TwoTimes);
+    }
+    if sign == Some('~') {
+        entry.logged = Some(length);
+    } else {
+        entry.start_offset = Some(length);
+    }
+    Ok(true)
+}
+
+pub fn parse_length(text: &str) -> Option<Duration> {
+    // 15m, 2h, 1h30 or 1h30m
+    let (hours, minutes) = match text.split_once('h') {
+        Some((hours, minutes)) => (number(hours)?, match minutes.strip_suffix('m').unwrap_or(minutes) {
+            "" => 0,
+            minutes => number(minutes)?,
+        }),
+        None => (0, number(text.strip_suffix('m')?)?),
+    };
+    let length = Duration::hours(hours) + Duration::minutes(minutes);
+    if length > Duration::zero() {
+        Some(length)
+    } else {
+        None
+    }
+}
+
+fn number(text: &str) -> Option<i64> {
+    if text.is_empty() || !text.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    text.parse::<u32>().ok().map(i64::from)
+}
+
+pub fn format_length(length: Duration) -> String {
+    // 1h 30m, for the preview under the task input
+    let hours = length.num_hours();
+    let minutes = length.num_minutes() % 60;
+    match (hours, minutes) {
+        (0, minutes) => format!("{}m", minutes),
+        (hours, 0) => format!("{}h", hours),
+        (hours, minutes) => format!("{}h {}m", hours, minutes),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tags(tags: &[&str]) -> Vec<String> {
+        tags.iter().map(|x| x.to_string()).collect()
+    }
+
+    #[test]
+    fn parses_name_and_tags() {
+        let entry = parse("Write report #Docs #other tag #docs").unwrap();
+        assert_eq!(entry.task_name, "Write report");
+        assert_eq!(entry.tags, tags(&["docs", "other tag"]));
+        assert_eq!(entry.project, None);
+        assert!(!entry.billable);
+        assert_eq!(entry.tag_list(), "docs #other tag");
+    }
+
+    #[test]
+    fn parses_quoted_tags() {
+        let entry = parse("Review #\"c# code\" #work").unwrap();
+        assert_eq!(entry.task_name, "Review");
+        assert_eq!(entry.tags, tags(&["c# code", "work"]));
+        assert_eq!(entry.tag_list(), "\"c# code\" #work");
+        assert_eq!(split_tags(&entry.tag_list()), entry.tags);
+    }
+
+    #[test]
+    fn parses_markers_in_any_order() {
+        let entry = parse("$ Call @acme client #sales -15m").unwrap();
+        assert_eq!(entry.task_name, "Call client");
+        assert_eq!(entry.tags, tags(&["sales"]));
+        assert_eq!(entry.project.as_deref(), Some("acme"));
+        assert!(entry.billable);
+        assert_eq!(entry.start_offset, Some(Duration::minutes(15)));
+        assert_eq!(entry.logged, None);
+        assert_eq!(entry.task_text(), "Call client #sales @acme $");
+    }
+
+    #[test]
+    fn leaves_markers_inside_words_alone() {
+        // Only a bare $ and @ at the start of a word are markers
+        let entry = parse("Pay $5 to me@example.com @").unwrap();
+        assert_eq!(entry.task_name, "Pay $5 to me@example.com @");
+        assert_eq!(entry.project, None);
+        assert!(!entry.billable);
+
+        // Words that only look a bit like times are part of the name
+        let entry = parse("Call +44 about item -3 - or ~ +1x").unwrap();
+        assert_eq!(entry.task_name, "Call +44 about item -3 - or ~ +1x");
+        assert_eq!(entry.start_offset, None);
+    }
+
+    #[test]
+    fn markers_after_tags_are_still_markers() {
+        let entry = parse("Budget #q1 $").unwrap();
+        assert!(entry.billable);
+        assert_eq!(entry.tags, tags(&["q1"]));
+    }
+
+    #[test]
+    fn parses_offsets_and_lengths() {
+        assert_eq!(parse("Task +10m").unwrap().start_offset, Some(Duration::minutes(10)));
+        assert_eq!(parse("Task -1h30").unwrap().start_offset, Some(Duration::minutes(90)));
+        let entry = parse("Meeting ~2h").unwrap();
+        assert_eq!(entry.logged, Some(Duration::hours(2)));
+        assert_eq!(entry.start_offset, None);
+    }
+
+    #[test]
+    fn rejects_bad_input() {
+        assert_eq!(parse(""), Err(ParseError::EmptyName));
+        assert_eq!(parse("#only tags @project"), Err(ParseError::EmptyName));
+        assert_eq!(parse("Task #\"\""), Err(ParseError::EmptyTag));
+        assert_eq!(parse("Task #\"open"), Err(ParseError::UnclosedQuote));
+        assert_eq!(parse("Task @one @two"), Err(ParseError::TwoProjects));
+        assert_eq!(parse("Task -10m ~1h"), Err(ParseError::TwoTimes));
+        assert_eq!(parse("Task ~1x"), Err(ParseError::BadDuration("~1x".to_string())));
+        assert_eq!(parse("Task -0m"), Err(ParseError::BadDuration("-0m".to_string())));
+        assert_eq!(parse("Task -1hm5"), Err(ParseError::BadDuration("-1hm5".to_string())));
+    }
+
+    #[test]
+    fn rejects_times_over_a_day() {
+        assert_eq!(parse("Task -24h").unwrap().start_offset, Some(Duration::hours(24)));
+        assert_eq!(parse("Task -24h1m"), Err(ParseError::TooLong("-24h1m".to_string())));
+        assert_eq!(parse("Task ~1441m"), Err(ParseError::TooLong("~1441m".to_string())));
+        assert_eq!(parse("Task -4294967295h"), Err(ParseError::TooLong("-4294967295h".to_string())));
+        // Too big for a number at all
+        assert_eq!(parse("Task -4294967296h"), Err(ParseError::BadDuration("-4294967296h".to_string())));
+    }
+
+    #[test]
+    fn parses_lengths() {
+        assert_eq!(parse_length("15m"), Some(Duration::minutes(15)));
+        assert_eq!(parse_length("2h"), Some(Duration::hours(2)));
+        assert_eq!(parse_length("1h30"), Some(Duration::minutes(90)));
+        assert_eq!(parse_length("1h30m"), Some(Duration::minutes(90)));
+        assert_eq!(parse_length("0h90"), Some(Duration::minutes(90)));
+        assert_eq!(parse_length("4294967295h"), Some(Duration::hours(4294967295)));
+        assert_eq!(parse_length(""), None);
+        assert_eq!(parse_length("15"), None);
+        assert_eq!(parse_length("0m"), None);
+        assert_eq!(parse_length("h"), None);
+        assert_eq!(parse_length("1.5h"), None);
+        assert_eq!(parse_length("-5m"), None);
+        assert_eq!(parse_length("4294967296h"), None);
+    }
+
+    #[test]
+    fn formats_lengths() {
+        assert_eq!(format_length(Duration::minutes(45)), "45m");
+        assert_eq!(format_length(Duration::hours(2)), "2h");
+        assert_eq!(format_length(Duration::minutes(90)), "1h 30m");
+    }
+
+    #[test]
+    fn splits_task_text() {
+        assert_eq!(split_task_text("Call @acme #sales $"), ("Call".to_string(), "sales".to_string()));
+        // Text that doesn't parse is split the old way
+        assert_eq!(split_task_text("Task @a @b #work"), ("Task @a @b".to_string(), "work".to_string()));
+    }
+}
@@ -0,0 +1,187 @@
+// Furtherance - Track your time without being tracked
+// Copyright (C) 2022  Ricky Kresslein <rk@lakoliu.com>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use gettextrs::*;
+use gtk::prelude::*;
+use gtk::{gio, glib};
+use log::debug;
+use once_cell::sync::Lazy;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::SystemTime;
+
+use crate::FurtheranceApplication;
+use crate::database;
+use crate::utils;
+
+const INTERFACE_NAME: &str = "org.gnome.Shell.SearchProvider2";
+// Has to match ObjectPath in the .search-provider.ini file
+const OBJECT_PATH: &str = "/com/lakoliu/Furtherance/SearchProvider";
+const MAX_RESULTS: usize = 10;
+
+// Every task as lowercase text to search and as it is shown, with when the database was read
+type SearchCache = Option<(Option<SystemTime>, Vec<(String, String)>)>;
+static SEARCH_CACHE: Lazy<Mutex<SearchCache>> = Lazy::new(|| Mutex::new(None));
+
+const INTERFACE_XML: &str = r#"
+<node>
+  <interface name="org.gnome.Shell.SearchProvider2">
+    <method name="GetInitialResultSet">
+      <arg type="as" name="terms" direction="in"/>
+      <arg type="as" name="results" direction="out"/>
+    </method>
+    <method name="GetSubsearchResultSet">
+      <arg type="as" name="previous_results" direction="in"/>
+      <arg type="as" name="terms" direction="in"/>
+      <arg type="as" name="results" direction="out"/>
+    </method>
+    <method name="GetResultMetas">
+      <arg type="as" name="identifiers" direction="in"/>
+      <arg type="aa{sv}" name="metas" direction="out"/>
+    </method>
+    <method name="ActivateResult">
+      <arg type="s" name="identifier" direction="in"/>
+      <arg type="as" name="terms" direction="in"/>
+      <arg type="u" name="timestamp" direction="in"/>
+    </method>
+    <method name="LaunchSearch">
+      <arg type="as" name="terms" direction="in"/>
+      <arg type="u" name="timestamp" direction="in"/>
+    </method>
+  </interface>
+</node>
+"#;
+
+pub fn register(app: &FurtheranceApplication) {
+    let connection = match app.dbus_connection() {
+        Some(connection) => connection,
+        None => return,
+    };
+    let interface_info = match gio::DBusNodeInfo::for_xml(INTERFACE_XML)
+        .ok()
+        .and_then(|node_info| node_info.lookup_interface(INTERFACE_NAME)) {
+        Some(interface_info) => interface_info,
+        None => return,
+    };
+
+    let registered = connection.register_object(
+        OBJECT_PATH,
+        &interface_info,
+        |_, _, _, _, method_name, parameters, invocation| {
+            // Keep the app around between searches when it was started for them
+            let app = FurtheranceApplication::default();
+            app.hold();
+            app.release();
+
+            match method_name {
+                "GetInitialResultSet" => {
+                    let (terms,) = parameters.get::<(Vec<String>,)>().unwrap_or_default();
+                    invocation.return_value(Some(&(search(&terms),).to_variant()));
+                }
+                "GetSubsearchResultSet" => {
+                    let (_, terms) = parameters.get::<(Vec<String>, Vec<String>)>().unwrap_or_default();
+                    invocation.return_value(Some(&(search(&terms),).to_variant()));
+                }
+                "GetResultMetas" => {
+                    let (identifiers,) = parameters.get::<(Vec<String>,)>().unwrap_or_default();
+                    invocation.return_value(Some(&(result_metas(&identifiers),).to_variant()));
+                }
+                "ActivateResult" => {
+                    if let Some((identifier, _, _)) = parameters.get::<(String, Vec<String>, u32)>() {
+                        activate_result(&app, &identifier);
+                    }
+                    invocation.return_value(None);
+                }
+                "LaunchSearch" => {
+                    app.activate();
+                    invocation.return_value(None);
+                }
+                _ => invocation.return_dbus_error("org.freedesktop.DBus.Error.UnknownMethod", method_name),
+            }
+        },
+        |_, _, _, _, _| "".to_variant(),
+        |_, _, _, _, _, _| false,
+    );
+    if let Err(err) = registered {
+        debug!("Could not export the search provider: {}", err);
+    }
+}
+
+fn search(terms: &[String]) -> Vec<String> {
+    // Tasks that match every term in their name or tags, most recent first
+    let terms: Vec<String> = terms.iter().map(|x| x.trim_start_matches('#').to_lowercase()).collect();
+    let mut cache = SEARCH_CACHE.lock().unwrap();
+    // Searches come in with every key press, so the tasks are only read again after a change
+    let modified = database::modified();
+    if !matches!(&*cache, Some((cached_at, _)) if *cached_at == modified) {
+        let task_texts = database::task_names_and_tags()
+            .unwrap_or_default()
+            .iter()
+            .map(|(task_name, tags)| {
+                let task_text = utils::task_text(task_name, tags);
+                (task_text.to_lowercase(), task_text)
+            })
+            .collect();
+        *cache = Some((modified, task_texts));
+    }
+
+    let mut results: Vec<String> = Vec::new();
+    for (searchable, task_text) in &cache.as_ref().unwrap().1 {
+        if !results.contains(task_text) && terms.iter().all(|term| searchable.contains(term)) {
+            results.push(task_text.clone());
+            if results.len() == MAX_RESULTS {
+                break;
+            }
+        }
+    }
+    results
+}
+
+fn result_metas(identifiers: &[String]) -> Vec<HashMap<String, glib::Variant>> {
+    identifiers.iter()
+        .map(|identifier| {
+            let (task_name, tag_list) = utils::split_tags_and_task(identifier);
+            let description = if tag_list.is_empty() {
+                gettext("Start this task")
+            } else {
+                format!("#{}", tag_list)
+            };
+            let mut meta: HashMap<String, glib::Variant> = HashMap::new();
+            meta.insert("id".to_string(), identifier.to_variant());
+            meta.insert("name".to_string(), task_name.to_variant());
+            meta.insert("description".to_string(), description.to_variant());
+            meta
+        })
+        .collect()
+}
+
+fn activate_result(app: &FurtheranceApplication, identifier: &str) {
+    // Start the task again, the same way as from the task list
+    let window = match app.main_window() {
+        Some(window) => window,
+        None => return,
+    };
+    let (task_name, tag_list) = utils::split_tags_and_task(identifier);
+    let task = database::retrieve()
+        .unwrap_or_default()
+        .into_iter()
+        .rev()
+        .find(|task| task.task_name == task_name && task.tags == tag_list);
+    if let Some(task) = task {
+        window.duplicate_task(task);
+    }
+    window.present();
+}
@@ -42,3 +42,8 @@ pub fn get_int(key: &str) -> i32 {
     let settings = get_settings();
     settings.int(key)
 }
+
+pub fn get_string(key: &str) -> String {
+    let settings = get_settings();
+    settings.string(key).to_string()
+}
@@ -0,0 +1,283 @@
+// Furtherance - Track your time without being tracked
+// Copyright (C) 2022  Ricky Kresslein <rk@lakoliu.com>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use chrono::{DateTime, Duration, Local};
+
+// When a pause started and ended
+pub type Pause = (DateTime<Local>, DateTime<Local>);
+
+// The timer only stores when things happened. Elapsed time is worked out
+// from the clock on every tick, so it can't drift after a suspend or
+// when the main loop is busy.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub enum TimerState {
+    #[default]
+    Idle,
+    Running {
+        start_time: DateTime<Local>,
+        pauses: Vec<Pause>,
+    },
+    Paused {
+        start_time: DateTime<Local>,
+        pauses: Vec<Pause>,
+        paused_at: DateTime<Local>,
+    },
+    // Discarded idle time is a pause, so it doesn't count down
+    Pomodoro {
+        start_time: DateTime<Local>,
+        length: Duration,
+        pauses: Vec<Pause>,
+    },
+    // Not a task, so it never gets saved
+    Break {
+        start_time: DateTime<Local>,
+        length: Duration,
+        long: bool,
+    },
+}
+
+// What the watch should show after a tick
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Tick {
+    Watch(i64),
+    PomodoroOver,
+    BreakOver,
+}
+
+impl TimerState {
+    pub fn running(start_time: DateTime<Local>) -> Self {
+        TimerState::Running { start_time, pauses: Vec::new() }
+    }
+
+    pub fn pomodoro(start_time: DateTime<Local>, length: Duration) -> Self {
+        TimerState::Pomodoro { start_time, length, pauses: Vec::new() }
+    }
+
+    pub fn take_break(start_time: DateTime<Local>, length: Duration, long: bool) -> Self {
+        TimerState::Break { start_time, length, long }
+    }
+
+    pub fn start_time(&self) -> Option<DateTime<Local>> {
+        // When the task started, breaks don't have one
+        match *self {
+            TimerState::Idle | TimerState::Break { .. } => None,
+            TimerState::Running { start_time, .. }
+            | TimerState::Paused { start_time, .. }
+            | TimerState::Pomodoro { start_time, .. } => Some(start_time),
+        }
+    }
+
+    pub fn pauses(&self) -> &[Pause] {
+        // Finished pauses, the one going on counts once the timer is resumed
+        match self {
+            TimerState::Running { pauses, .. }
+            | TimerState::Paused { pauses, .. }
+            | TimerState::Pomodoro { pauses, .. } => pauses,
+            _ => &[],
+        }
+    }
+
+    pub fn paused(&self) -> Duration {
+        self.pauses().iter().fold(Duration::zero(), |paused, (pause_start, pause_stop)| {
+            paused + (*pause_stop - *pause_start)
+        })
+    }
+
+    pub fn elapsed(&self, now: DateTime<Local>) -> Duration {
+        // Time spent on the task, without pauses
+        match *self {
+            TimerState::Idle => Duration::zero(),
+            TimerState::Running { start_time, .. }
+            | TimerState::Pomodoro { start_time, .. } => now - start_time - self.paused(),
+            TimerState::Paused { start_time, paused_at, .. } => paused_at - start_time - self.paused(),
+            TimerState::Break { start_time, .. } => now - start_time,
+        }
+        .max(Duration::zero())
+    }
+
+    pub fn remaining(&self, now: DateTime<Local>) -> Option<Duration> {
+        // Only pomodoros and breaks count down
+        match *self {
+            TimerState::Pomodoro { length, .. }
+            | TimerState::Break { length, .. } => Some((length - self.elapsed(now)).max(Duration::zero())),
+            _ => None,
+        }
+    }
+
+    pub fn with_start_time(&self, start_time: DateTime<Local>) -> Self {
+        // The same timer, started at a different time
+        match self {
+            TimerState::Running { pauses, .. } => TimerState::Running { start_time, pauses: pauses.clone() },
+            TimerState::Paused { pauses, paused_at, .. } => {
+                TimerState::Paused { start_time, pauses: pauses.clone(), paused_at: *paused_at }
+            }
+            TimerState::Pomodoro { length, pauses, .. } => {
+                TimerState::Pomodoro { start_time, length: *length, pauses: pauses.clone() }
+            }
+            state => state.clone(),
+        }
+    }
+
+    pub fn skip(&self, from: DateTime<Local>, to: DateTime<Local>) -> Self {
+        // Leave out time the user was away, as a pause that shows in the task's details
+        match self {
+            TimerState::Running { start_time, pauses } => {
+                let mut pauses = pauses.clone();
+                pauses.push((from, to));
+                TimerState::Running { start_time: *start_time, pauses }
+            }
+            TimerState::Pomodoro { start_time, length, pauses } => {
+                let mut pauses = pauses.clone();
+                pauses.push((from, to));
+                TimerState::Pomodoro { start_time: *start_time, length: *length, pauses }
+            }
+            state => state.clone(),
+        }
+    }
+
+    pub fn pause(&self, now: DateTime<Local>) -> Self {
+        match self {
+            TimerState::Running { start_time, pauses } => {
+                TimerState::Paused { start_time: *start_time, pauses: pauses.clone(), paused_at: now }
+            }
+            state => state.clone(),
+        }
+    }
+
+    pub fn resume(&self, now: DateTime<Local>) -> Self {
+        match self {
+            TimerState::Paused { start_time, pauses, paused_at } => {
+                let mut pauses = pauses.clone();
+                pauses.push((*paused_at, now));
+                TimerState::Running { start_time: *start_time, pauses }
+            }
+            state => state.clone(),
+        }
+    }
+
+    pub fn tick(&self, now: DateTime<Local>) -> Option<Tick> {
+        match self.remaining(now) {
+            Some(remaining) if remaining <= Duration::zero() => match self {
+                TimerState::Break { .. } => Some(Tick::BreakOver),
+                _ => Some(Tick::PomodoroOver),
+            },
+            Some(remaining) => Some(Tick::Watch(remaining.num_seconds())),
+            None if *self == TimerState::Idle => None,
+            None => Some(Tick::Watch(self.elapsed(now).num_seconds())),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn at(minutes: i64) -> DateTime<Local> {
+        Local.ymd(2022, 5, 2).and_hms(9, 0, 0) + Duration::minutes(minutes)
+    }
+
+    #[test]
+    fn idle_does_not_tick() {
+        assert_eq!(TimerState::Idle.tick(at(0)), None);
+        assert_eq!(TimerState::Idle.elapsed(at(5)), Duration::zero());
+    }
+
+    #[test]
+    fn running_counts_up() {
+        let timer = TimerState::running(at(0));
+        assert_eq!(timer.tick(at(0)), Some(Tick::Watch(0)));
+        assert_eq!(timer.tick(at(90)), Some(Tick::Watch(90 * 60)));
+        // A clock set back doesn't make the watch negative
+        assert_eq!(timer.tick(at(-5)), Some(Tick::Watch(0)));
+    }
+
+    #[test]
+    fn paused_stands_still() {
+        let timer = TimerState::running(at(0)).pause(at(10));
+        assert_eq!(timer.tick(at(10)), Some(Tick::Watch(10 * 60)));
+        assert_eq!(timer.tick(at(45)), Some(Tick::Watch(10 * 60)));
+        assert!(timer.pauses().is_empty());
+
+        let timer = timer.resume(at(30));
+        assert_eq!(timer.pauses(), &[(at(10), at(30))]);
+        assert_eq!(timer.paused(), Duration::minutes(20));
+        assert_eq!(timer.tick(at(40)), Some(Tick::Watch(20 * 60)));
+    }
+
+    #[test]
+    fn pauses_add_up() {
+        let timer = TimerState::running(at(0))
+            .pause(at(10))
+            .resume(at(15))
+            .pause(at(20))
+            .resume(at(30));
+        assert_eq!(timer.pauses(), &[(at(10), at(15)), (at(20), at(30))]);
+        assert_eq!(timer.elapsed(at(60)), Duration::minutes(45));
+        // Moving the start time keeps the pauses
+        let timer = timer.with_start_time(at(-15));
+        assert_eq!(timer.start_time(), Some(at(-15)));
+        assert_eq!(timer.elapsed(at(60)), Duration::minutes(60));
+    }
+
+    #[test]
+    fn pause_only_applies_while_running() {
+        let pomodoro = TimerState::pomodoro(at(0), Duration::minutes(25));
+        assert_eq!(pomodoro.pause(at(5)), pomodoro);
+        let running = TimerState::running(at(0));
+        assert_eq!(running.resume(at(5)), running);
+    }
+
+    #[test]
+    fn pomodoro_break_and_running() {
+        let pomodoro = TimerState::pomodoro(at(0), Duration::minutes(25));
+        assert_eq!(pomodoro.start_time(), Some(at(0)));
+        assert_eq!(pomodoro.tick(at(0)), Some(Tick::Watch(25 * 60)));
+        assert_eq!(pomodoro.tick(at(20)), Some(Tick::Watch(5 * 60)));
+        assert_eq!(pomodoro.tick(at(25)), Some(Tick::PomodoroOver));
+        assert_eq!(pomodoro.tick(at(30)), Some(Tick::PomodoroOver));
+
+        let short_break = TimerState::take_break(at(25), Duration::minutes(5), false);
+        assert_eq!(short_break.start_time(), None);
+        assert_eq!(short_break.tick(at(27)), Some(Tick::Watch(3 * 60)));
+        assert_eq!(short_break.tick(at(30)), Some(Tick::BreakOver));
+
+        // Continuing after a pomodoro counts up from when it started
+        let running = TimerState::running(pomodoro.start_time().unwrap());
+        assert_eq!(running.remaining(at(40)), None);
+        assert_eq!(running.tick(at(40)), Some(Tick::Watch(40 * 60)));
+    }
+
+    #[test]
+    fn skip_leaves_out_time_away() {
+        let timer = TimerState::running(at(0)).skip(at(10), at(40));
+        assert_eq!(timer.start_time(), Some(at(0)));
+        assert_eq!(timer.pauses(), &[(at(10), at(40))]);
+        assert_eq!(timer.tick(at(60)), Some(Tick::Watch(30 * 60)));
+
+        // A pomodoro counts down longer, but still started when it did
+        let pomodoro = TimerState::pomodoro(at(0), Duration::minutes(25)).skip(at(10), at(20));
+        assert_eq!(pomodoro.start_time(), Some(at(0)));
+        assert_eq!(pomodoro.pauses(), &[(at(10), at(20))]);
+        assert_eq!(pomodoro.tick(at(30)), Some(Tick::Watch(5 * 60)));
+        assert_eq!(pomodoro.tick(at(35)), Some(Tick::PomodoroOver));
+
+        // Nothing to leave out while paused or on a break
+        let paused = TimerState::running(at(0)).pause(at(5));
+        assert_eq!(paused.skip(at(10), at(20)), paused);
+        assert_eq!(TimerState::Idle.skip(at(10), at(20)), TimerState::Idle);
+    }
+}
@@ -16,10 +16,12 @@
 
 use adw::prelude::*;
 use adw::subclass::prelude::*;
+use gettextrs::*;
 use gtk::glib;
 use gtk::subclass::prelude::*;
 use gtk::CompositeTemplate;
 
+use crate::idle;
 use crate::settings_manager;
 use crate::ui::FurtheranceWindow;
 use crate::FurtheranceApplication;
@@ -64,11 +66,26 @@ mod imp {
         pub pomodoro_expander: TemplateChild<adw::ExpanderRow>,
         #[template_child]
         pub pomodoro_spin: TemplateChild<gtk::SpinButton>,
+        #[template_child]
+        pub short_break_spin: TemplateChild<gtk::SpinButton>,
+        #[template_child]
+        pub long_break_spin: TemplateChild<gtk::SpinButton>,
+        #[template_child]
+        pub long_break_interval_spin: TemplateChild<gtk::SpinButton>,
+        #[template_child]
+        pub auto_start_breaks_switch: TemplateChild<gtk::Switch>,
+        #[template_child]
+        pub auto_start_pomodoros_switch: TemplateChild<gtk::Switch>,
 
         #[template_child]
         pub autosave_expander: TemplateChild<adw::ExpanderRow>,
         #[template_child]
         pub autosave_spin: TemplateChild<gtk::SpinButton>,
+
+        #[template_child]
+        pub export_group: TemplateChild<adw::PreferencesGroup>,
+        #[template_child]
+        pub timeclock_accounts_entry: TemplateChild<gtk::Entry>,
     }
 
     #[glib::object_subclass]
@@ -128,6 +145,12 @@ impl FurPreferencesWindow {
         let manager = adw::StyleManager::default();
         let support_darkmode = manager.system_supports_color_schemes();
         imp.appearance_group.set_visible(!support_darkmode);
+
+        // Idle time comes from the desktop, and not every desktop shares it
+        if idle::source_name().is_none() {
+            imp.notify_of_idle_expander.set_subtitle(&gettext("Not available on this desktop"));
+            imp.notify_of_idle_expander.set_sensitive(false);
+        }
     }
 
     fn setup_signals(&self) {
@@ -199,6 +222,36 @@ impl FurPreferencesWindow {
             "value"
         );
 
+        settings_manager::bind_property(
+            "pomodoro-short-break",
+            &*imp.short_break_spin,
+            "value"
+        );
+
+        settings_manager::bind_property(
+            "pomodoro-long-break",
+            &*imp.long_break_spin,
+            "value"
+        );
+
+        settings_manager::bind_property(
+            "pomodoro-long-break-interval",
+            &*imp.long_break_interval_spin,
+            "value"
+        );
+
+        settings_manager::bind_property(
+            "pomodoro-auto-start-breaks",
+            &*imp.auto_start_breaks_switch,
+            "active"
+        );
+
+        settings_manager::bind_property(
+            "pomodoro-auto-start-pomodoros",
+            &*imp.auto_start_pomodoros_switch,
+            "active"
+        );
+
         settings_manager::bind_property(
             "autosave",
             &*imp.autosave_expander,
@@ -211,6 +264,12 @@ impl FurPreferencesWindow {
             "value"
         );
 
+        settings_manager::bind_property(
+            "timeclock-accounts",
+            &*imp.timeclock_accounts_entry,
+            "text"
+        );
+
         imp.dark_theme_switch.connect_active_notify(move |_|{
             let app = FurtheranceApplication::default();
             app.update_light_dark();
@@ -250,6 +309,11 @@ impl FurPreferencesWindow {
             let window = FurtheranceWindow::default();
             window.refresh_timer();
         });
+
+        imp.long_break_interval_spin.connect_value_changed(move |_|{
+            let window = FurtheranceWindow::default();
+            window.update_pomodoro_title();
+        });
     }
 }
 
@@ -18,13 +18,21 @@ use adw::subclass::prelude::*;
 use gettextrs::*;
 use glib::clone;
 use gtk::subclass::prelude::*;
-use gtk::{glib, prelude::*, CompositeTemplate};
+use gtk::{gio, glib, prelude::*, CompositeTemplate};
 use chrono::{DateTime, NaiveDate, Local, Duration, Date, Datelike, offset::TimeZone};
 use itertools::Itertools;
+use std::error::Error;
+use std::fs;
+use std::path::Path;
+use std::rc::Rc;
 
 use crate::FurtheranceApplication;
 use crate::ui::FurtheranceWindow;
 use crate::database;
+use crate::export;
+use crate::quick_entry;
+use crate::settings_manager;
+use crate::utils;
 
 mod imp {
     use super::*;
@@ -162,275 +170,301 @@ impl FurReport {
         imp.results_tree.append_column(&duration_column);
         imp.results_tree.set_enable_search(false);
 
+        self.setup_export_actions();
         self.refresh_report();
     }
 
-    fn refresh_report(&self) {
-        let imp = imp::FurReport::from_instance(self);
-        imp.format_error.set_visible(false);
-        imp.start_end_error.set_visible(false);
+    fn setup_export_actions(&self) {
+        let export_timeclock_action = gio::SimpleAction::new("export-timeclock", None);
+        export_timeclock_action.connect_activate(clone!(@weak self as this => move |_, _| {
+            this.export_timeclock();
+        }));
 
-        let results_model = gtk::TreeStore::new(&[String::static_type(), String::static_type()]);
+        let export_org_action = gio::SimpleAction::new("export-org", None);
+        export_org_action.connect_activate(clone!(@weak self as this => move |_, _| {
+            this.export_org();
+        }));
 
-        let mut task_list = database::retrieve().unwrap();
-        task_list.reverse();
+        let export_markdown_action = gio::SimpleAction::new("export-markdown", None);
+        export_markdown_action.connect_activate(clone!(@weak self as this => move |_, _| {
+            this.export_markdown();
+        }));
 
-        // Get date range
-        let active_range = imp.range_combo.active_id().unwrap();
-        let today = Local::today();
-        let range_start_date: Date<Local>;
-        let mut range_end_date = today;
-        if active_range == "week_item" {
-            range_start_date = today - Duration::days(6);
-        } else if active_range == "month_item" {
-            let days_ago = today.day() - 1;
-            range_start_date = today - Duration::days(days_ago.into());
-        } else if active_range == "30_days_item" {
-            range_start_date = today - Duration::days(30);
-        } else if active_range == "six_months_item" {
-            range_start_date = today - Duration::days(180);
-        } else if active_range == "year_item" {
-            range_start_date = today - Duration::days(365);
-        } else {
-            let input_start_date = NaiveDate::parse_from_str(&imp.start_date_entry.text(), "%m/%d/%Y");
-            let input_end_date = NaiveDate::parse_from_str(&imp.end_date_entry.text(), "%m/%d/%Y");
-            // Check if user entered dates properly
-            if let Err(_) = input_start_date {
-                imp.format_error.set_visible(true);
-                results_model.clear();
-                imp.results_tree.set_model(Some(&results_model));
-                return
-            }
-            if let Err(_) = input_end_date {
-                imp.format_error.set_visible(true);
-                results_model.clear();
-                imp.results_tree.set_model(Some(&results_model));
-                return
-            }
-            // Start date cannot be after end date
-            if (input_end_date.unwrap() - input_start_date.unwrap()).num_days() < 0 {
-                imp.start_end_error.set_visible(true);
-                results_model.clear();
-                return
-            }
-            range_start_date = Local.from_local_date(&input_start_date.unwrap()).unwrap();
-            range_end_date = Local.from_local_date(&input_end_date.unwrap()).unwrap();
+        let export_html_action = gio::SimpleAction::new("export-html", None);
+        export_html_action.connect_activate(clone!(@weak self as this => move |_, _| {
+            this.export_html();
+        }));
+        let export_ods_action = gio::SimpleAction::new("export-ods", None);
+        export_ods_action.connect_activate(clone!(@weak self as this => move |_, _| {
+            this.export_ods();
+        }));
+        let print_action = gio::SimpleAction::new("print", None);
+        print_action.connect_activate(clone!(@weak self as this => move |_, _| {
+            this.print();
+        }));
+        let export_pdf_action = gio::SimpleAction::new("export-pdf", None);
+        export_pdf_action.connect_activate(clone!(@weak self as this => move |_, _| {
+            this.export_pdf();
+        }));
+
+        let actions = gio::SimpleActionGroup::new();
+        self.insert_action_group("report", Some(&actions));
+        actions.add_action(&export_timeclock_action);
+        actions.add_action(&export_org_action);
+        actions.add_action(&export_markdown_action);
+        actions.add_action(&export_html_action);
+        actions.add_action(&export_ods_action);
+        actions.add_action(&print_action);
+        actions.add_action(&export_pdf_action);
+    }
+
+    fn export_timeclock(&self) {
+        let (_, _, tasks_in_range) = match self.tasks_in_range() {
+            Some(range) => range,
+            None => return,
+        };
+        let timeclock = export::to_timeclock(&tasks_in_range,
+                                             &database::pauses_by_task().unwrap_or_default(),
+                                             &settings_manager::get_string("timeclock-accounts"));
+
+        self.save_export("furtherance.timeclock", move |path| {
+            fs::write(path, &timeclock)?;
+            Ok(())
+        });
+    }
+
+    fn export_org(&self) {
+        let (start_date, end_date, tasks_in_range) = match self.tasks_in_range() {
+            Some(range) => range,
+            None => return,
+        };
+        let org = export::to_org(&tasks_in_range,
+                                 &database::pauses_by_task().unwrap_or_default(),
+                                 start_date,
+                                 end_date);
+
+        self.save_export("furtherance.org", move |path| {
+            fs::write(path, &org)?;
+            Ok(())
+        });
+    }
+
+    fn export_markdown(&self) {
+        let (start_date, end_date, tasks_in_range) = match self.tasks_in_range() {
+            Some(range) => range,
+            None => return,
+        };
+        let markdown = export::to_markdown(&tasks_in_range,
+                                           start_date,
+                                           end_date,
+                                           settings_manager::get_bool("show-seconds"));
+
+        self.save_export("furtherance.md", move |path| {
+            fs::write(path, &markdown)?;
+            Ok(())
+        });
+    }
+
+    fn export_html(&self) {
+        let (start_date, end_date, tasks_in_range) = match self.tasks_in_range() {
+            Some(range) => range,
+            None => return,
+        };
+        let html = export::to_html(&tasks_in_range, start_date, end_date);
+
+        self.save_export("furtherance.html", move |path| {
+            fs::write(path, &html)?;
+            Ok(())
+        });
+    }
+
+    fn export_ods(&self) {
+        let summary = match self.report_summary() {
+            Some(summary) => summary,
+            None => return,
+        };
+        let (_, _, tasks_in_range) = match self.tasks_in_range() {
+            Some(range) => range,
+            None => return,
+        };
+        self.save_export("furtherance.ods", move |path| {
+            fs::write(path, export::to_ods(&summary, &tasks_in_range)?)?;
+            Ok(())
+        });
+    }
+
+    fn print(&self) {
+        let report = match self.report_summary() {
+            Some(report) => report,
+            None => return,
+        };
+        if let Err(err) = export::print_report(&report, self) {
+            self.show_export_error(&gettext("Printing failed"), &err.to_string());
         }
+    }
 
-        let mut total_time: i64 = 0;
-        let mut tasks_in_range: Vec<(database::Task, i64)> = Vec::new();
-        let mut user_chosen_tags: Vec<String> = Vec::new();
-        let mut only_this_tag = false;
-        for task in task_list {
-            let start = DateTime::parse_from_rfc3339(&task.start_time).unwrap().with_timezone(&Local);
-            let stop = DateTime::parse_from_rfc3339(&task.stop_time).unwrap().with_timezone(&Local);
-            // Check if start time is in date range and if not remove it from task_list
-            let start_date = start.date();
-            if start_date >= range_start_date && start_date <= range_end_date {
-                // Sort by only selected tasks or tags if filter is selected
-                if imp.filter_check.is_active() && !imp.filter_entry.text().trim().is_empty() {
-                    if imp.filter_combo.active_id().unwrap() == "tasks_item" {
-                        // Create a vec of tasks to match from written tasks
-                        let chosen_tasks = imp.filter_entry.text();
-                        let mut split_tasks: Vec<&str> = chosen_tasks.trim().split(",").collect();
-                        // Trim whitespace around each task
-                        split_tasks = split_tasks.iter().map(|x| x.trim()).collect();
-                        // Don't allow empty tasks
-                        split_tasks.retain(|&x| !x.trim().is_empty());
-                        // Handle duplicate tasks
-                        split_tasks = split_tasks.into_iter().unique().collect();
-                        // Lowercase tags
-                        let lower_tasks: Vec<String> = split_tasks.iter().map(|x| x.to_lowercase()).collect();
-
-                        if lower_tasks.contains(&task.task_name.to_lowercase()) {
-                            let duration = stop - start;
-                            let duration = duration.num_seconds();
-                            tasks_in_range.push((task, duration));
-                            total_time += duration;
-                        }
+    fn export_pdf(&self) {
+        let report = match self.report_summary() {
+            Some(report) => report,
+            None => return,
+        };
+        self.save_export("furtherance.pdf", move |path| {
+            export::export_pdf(&report, path)?;
+            Ok(())
+        });
+    }
 
-                    } else if imp.filter_combo.active_id().unwrap() == "tags_item" {
-                        // Split user chosen tags
-                        let chosen_tasgs = imp.filter_entry.text();
-                        let mut split_tags: Vec<&str> = chosen_tasgs.trim().split(",").collect();
-                        // Trim whitespace around each tag
-                        split_tags = split_tags.iter().map(|x| x.trim()).collect();
-                        // Don't allow empty tags
-                        split_tags.retain(|&x| !x.trim().is_empty());
-                        // Handle duplicate tags
-                        split_tags = split_tags.into_iter().unique().collect();
-                        // Lowercase tags
-                        user_chosen_tags = split_tags.iter().map(|x| x.to_lowercase()).collect();
-
-                        // Split task's tags
-                        let mut split_tags: Vec<&str> = task.tags.trim().split("#").collect();
-                        // Trim whitespace around each tag
-                        split_tags = split_tags.iter().map(|x| x.trim()).collect();
-
-                        // Only keep tasks that contain the user's chosen tags
-                        split_tags.retain(|&x| user_chosen_tags.contains(&x.to_string()));
-                        if !split_tags.is_empty() {
-                            let duration = stop - start;
-                            let duration = duration.num_seconds();
-                            tasks_in_range.push((task, duration));
-                            total_time += duration;
+    fn report_summary(&self) -> Option<Rc<export::ReportSummary>> {
+        // Lay out the report as it is currently shown
+        let imp = imp::FurReport::from_instance(self);
+        let (start_date, end_date) = self.date_range()?;
+        let model = imp.results_tree.model()?;
+        let all_results_iter = model.iter_first()?;
+
+        let mut rows: Vec<export::ReportRow> = Vec::new();
+        if let Some(group_iter) = model.iter_children(Some(&all_results_iter)) {
+            loop {
+                rows.push(export::ReportRow {
+                    depth: 0,
+                    label: model.get::<String>(&group_iter, 0),
+                    duration: model.get::<i64>(&group_iter, 2),
+                });
+                if let Some(child_iter) = model.iter_children(Some(&group_iter)) {
+                    loop {
+                        rows.push(export::ReportRow {
+                            depth: 1,
+                            label: model.get::<String>(&child_iter, 0),
+                            duration: model.get::<i64>(&child_iter, 2),
+                        });
+                        if !model.iter_next(&child_iter) {
+                            break;
                         }
-
-                        only_this_tag = true;
                     }
-                } else {
-                    let duration = stop - start;
-                    let duration = duration.num_seconds();
-                    tasks_in_range.push((task, duration));
-                    total_time += duration;
+                }
+                if !model.iter_next(&group_iter) {
+                    break;
                 }
             }
         }
 
+        let sort_by = if imp.sort_by_task.is_active() {
+            gettext("Sorted by task")
+        } else {
+            gettext("Sorted by tag")
+        };
+        let filter = if imp.filter_check.is_active() && !self.filter_list().is_empty() {
+            if imp.filter_combo.active_id().unwrap() == "tasks_item" {
+                format!("{}, {}: {}", sort_by, gettext("Tasks"), self.filter_list().join(", "))
+            } else {
+                format!("{}, {}: #{}", sort_by, gettext("Tags"), self.filter_list().join(" #"))
+            }
+        } else {
+            format!("{}, {}", sort_by, gettext("all tasks"))
+        };
+
+        Some(Rc::new(export::ReportSummary {
+            date_range: format!("{} – {}",
+                                start_date.format("%b %e, %Y"),
+                                end_date.format("%b %e, %Y")),
+            filter,
+            rows,
+            total: model.get::<i64>(&all_results_iter, 2),
+        }))
+    }
+
+    fn save_export<F>(&self, file_name: &str, write_export: F)
+    where
+        F: Fn(&Path) -> Result<(), Box<dyn Error>> + 'static,
+    {
+        // Ask where to save the report, then write it there
+        let dialog = gtk::FileChooserNative::new(
+            Some(&gettext("Export Report")),
+            Some(self),
+            gtk::FileChooserAction::Save,
+            Some(&gettext("Export")),
+            Some(&gettext("Cancel")),
+        );
+        dialog.set_modal(true);
+        dialog.set_current_name(file_name);
+
+        dialog.connect_response(clone!(@weak self as this, @strong dialog => move |_, resp| {
+            if resp == gtk::ResponseType::Accept {
+                if let Some(path) = dialog.file().and_then(|file| file.path()) {
+                    if let Err(err) = write_export(&path) {
+                        this.show_export_error(&gettext("Export failed"), &err.to_string());
+                    }
+                }
+            }
+            dialog.destroy();
+        }));
+
+        dialog.show();
+    }
+
+    fn refresh_report(&self) {
+        let imp = imp::FurReport::from_instance(self);
+        // Durations are also kept in seconds for exporting
+        let results_model = gtk::TreeStore::new(&[String::static_type(), String::static_type(), i64::static_type()]);
+
+        let (_, _, tasks_in_range) = match self.tasks_in_range() {
+            Some(range) => range,
+            None => {
+                results_model.clear();
+                imp.results_tree.set_model(Some(&results_model));
+                return
+            }
+        };
+        let total_time: i64 = tasks_in_range.iter().map(|(_, duration)| duration).sum();
+        let total_pomodoros: i32 = tasks_in_range.iter().map(|(task, _)| task.pomodoros).sum();
+
+        // Only show the chosen tags when sorting by tag
+        let only_this_tag = imp.filter_check.is_active()
+            && imp.filter_combo.active_id().unwrap() == "tags_item"
+            && !imp.filter_entry.text().trim().is_empty();
+        let user_chosen_tags = self.filter_list();
+
         let all_tasks_iter:gtk::TreeIter;
         if tasks_in_range.is_empty() {
             all_tasks_iter = results_model.insert_with_values(None,
                                                                 None,
                                                                 &[
                                                                     (0, &gettext("No Results")),
-                                                                    (1, &"")
+                                                                    (1, &""),
+                                                                    (2, &0i64)
                                                                 ]);
         } else {
-            let total_time_str = FurReport::format_duration(total_time);
+            let total_time_str = utils::format_duration(total_time, true);
+            let mut all_results = gettext("All Results");
+            if total_pomodoros > 0 {
+                all_results = format!("{} ({} {})", all_results, total_pomodoros, gettext("pomodoros"));
+            }
             all_tasks_iter = results_model.insert_with_values(None,
                                                                     None,
                                                                     &[
-                                                                        (0, &gettext("All Results")),
-                                                                        (1, &total_time_str)
+                                                                        (0, &all_results),
+                                                                        (1, &total_time_str),
+                                                                        (2, &total_time)
                                                                     ]);
         }
 
         if imp.sort_by_task.is_active() {
-            let mut tasks_by_name: Vec<Vec<(database::Task, i64)>> = Vec::new();
-            for (task, task_duration) in tasks_in_range {
-                let mut unique = true;
-                for i in 0..tasks_by_name.len() {
-                    let (tbn, _) = &tasks_by_name[i][0];
-                    if tbn.task_name == task.task_name {
-                        tasks_by_name[i].push((task.clone(), task_duration));
-                        unique = false;
-                    }
-                }
-                if unique {
-                    // Add unique task to list for group name
-                    let mut new_name_list: Vec<(database::Task, i64)> = Vec::new();
-                    new_name_list.push((task.clone(), task_duration));
-                    tasks_by_name.push(new_name_list);
-                }
-            }
-
-            let mut sorted_tasks_by_duration: Vec<(String, i64, Vec<(String, i64)>)> = Vec::new();
-            for tbn in tasks_by_name {
-                let mut total_duration: i64 = 0;
-                let mut tags_dur: Vec<(String, i64)> = Vec::new();
-                let task_name = tbn[0].0.task_name.to_string();
-                for tbn_tuple in tbn {
-                    let (task, task_duration) = tbn_tuple;
-                    total_duration += task_duration;
-
-                    let mut split_tags: Vec<&str> = task.tags.split("#").collect();
-                    split_tags = split_tags.iter().map(|x| x.trim()).collect();
-                    split_tags.retain(|&x| !x.trim().is_empty());
-                    if !split_tags.is_empty() {
-                        let mut formatted_tags = split_tags.join(" #");
-                        formatted_tags = format!("#{}", formatted_tags);
-                        let mut unique = true;
-                        for i in 0..tags_dur.len() {
-                            let (tags, dur) = &tags_dur[i];
-                            if tags == &formatted_tags {
-                                let new_dur = dur + task_duration;
-                                tags_dur[i] = (formatted_tags.clone(), new_dur);
-                                unique = false;
-                            }
-                        }
-                        if unique {
-                            tags_dur.push((formatted_tags, task_duration))
-                        }
-                    }
-                }
-
-                // Sort tasks and tags in descending order by duration
-                tags_dur.sort_by_key(|k| k.1);
-                tags_dur.reverse();
-                sorted_tasks_by_duration.push((task_name, total_duration, tags_dur));
-                sorted_tasks_by_duration.sort_by_key(|k| k.1);
-                sorted_tasks_by_duration.reverse();
-            }
-
+            let sorted_tasks_by_duration = utils::durations_by_task(tasks_in_range);
             for stbd in sorted_tasks_by_duration {
                 let header_iter = results_model.append(Some(&all_tasks_iter));
                 for (task, task_duration) in stbd.2 {
                     let _child_iter = results_model.insert_with_values(
                         Some(&header_iter),
                         None,
-                        &[(0, &task), (1, &FurReport::format_duration(task_duration))]
+                        &[(0, &task), (1, &utils::format_duration(task_duration, true)), (2, &task_duration)]
                     );
                 }
                 results_model.set(
                     &header_iter,
-                    &[(0, &stbd.0), (1, &FurReport::format_duration(stbd.1))]
+                    &[(0, &stbd.0), (1, &utils::format_duration(stbd.1, true)), (2, &stbd.1)]
                 );
             }
         } else if imp.sort_by_tag.is_active() {
-            let mut tasks_by_tag: Vec<Vec<(String, database::Task, i64)>> = Vec::new();
-            for (task, task_duration) in tasks_in_range {
-                let mut split_tags: Vec<&str> = task.tags.split("#").collect();
-                // Trim whitespace around each tag
-                split_tags = split_tags.iter().map(|x| x.trim()).collect();
-                for tag in split_tags {
-                    let mut unique = true;
-                    for i in 0..tasks_by_tag.len() {
-                        let (tbt_tag, _, _) = &tasks_by_tag[i][0];
-                        if tbt_tag == tag {
-                            tasks_by_tag[i].push((tag.to_string(), task.clone(), task_duration));
-                            unique = false;
-                        }
-                    }
-                    if unique {
-                        // Add unique task to list for group name
-                        let mut new_name_list: Vec<(String, database::Task, i64)> = Vec::new();
-                        new_name_list.push((tag.to_string(), task.clone(), task_duration));
-                        tasks_by_tag.push(new_name_list);
-                    }
-                }
-            }
-
-            let mut sorted_tasks_by_duration: Vec<(String, i64, Vec<(String, i64)>)> = Vec::new();
-            for tbt in tasks_by_tag {
-                let mut total_duration: i64 = 0;
-                let mut tasks_dur: Vec<(String, i64)> = Vec::new();
-                let tag_name = format!("#{}", tbt[0].0);
-                for tbt_tuple in tbt {
-                    let (_, task, tag_duration) = tbt_tuple;
-                    total_duration += tag_duration;
-
-                    let mut unique = true;
-                    for i in 0..tasks_dur.len() {
-                        let (td_task, dur) = &tasks_dur[i];
-                        if td_task == &task.task_name {
-                            let new_dur = dur + tag_duration;
-                            tasks_dur[i] = (task.task_name.clone(), new_dur);
-                            unique = false;
-                        }
-                    }
-                    if unique {
-                        tasks_dur.push((task.task_name.clone(), tag_duration))
-                    }
-                }
-
-                // Sort tags and tasks in descending order by duration
-                tasks_dur.sort_by_key(|k| k.1);
-                tasks_dur.reverse();
-                sorted_tasks_by_duration.push((tag_name, total_duration, tasks_dur));
-                sorted_tasks_by_duration.sort_by_key(|k| k.1);
-                sorted_tasks_by_duration.reverse();
-            }
-
+            let sorted_tasks_by_duration = utils::durations_by_tag(tasks_in_range);
             for mut stbd in sorted_tasks_by_duration {
                 if !only_this_tag || (only_this_tag && user_chosen_tags.contains(&stbd.0[1..].to_string())) {
                     let header_iter = results_model.append(Some(&all_tasks_iter));
@@ -438,7 +472,7 @@ impl FurReport {
                         let _child_iter = results_model.insert_with_values(
                             Some(&header_iter),
                             None,
-                            &[(0, &task), (1, &FurReport::format_duration(task_duration))]
+                            &[(0, &task), (1, &utils::format_duration(task_duration, true)), (2, &task_duration)]
                         );
                     }
                     if stbd.0 == "#" {
@@ -446,7 +480,7 @@ impl FurReport {
                     }
                     results_model.set(
                         &header_iter,
-                        &[(0, &stbd.0), (1, &FurReport::format_duration(stbd.1))]
+                        &[(0, &stbd.0), (1, &utils::format_duration(stbd.1, true)), (2, &stbd.1)]
                     );
                 }
             }
@@ -458,12 +492,116 @@ impl FurReport {
         imp.results_tree.expand_row(&all_tasks_path, false);
     }
 
-    fn format_duration(total_time: i64) -> String {
-         // Format total time to readable string
-        let h = total_time / 3600;
-        let m = total_time % 3600 / 60;
-        let s = total_time % 60;
-        format!("{:02}:{:02}:{:02}", h, m, s)
+    fn show_export_error(&self, title: &str, message: &str) {
+        let dialog = gtk::MessageDialog::new(
+            Some(self),
+            gtk::DialogFlags::MODAL,
+            gtk::MessageType::Error,
+            gtk::ButtonsType::Ok,
+            title,
+        );
+        dialog.set_secondary_text(Some(message));
+        dialog.connect_response(|dialog, _| dialog.close());
+        dialog.show();
+    }
+
+    fn date_range(&self) -> Option<(Date<Local>, Date<Local>)> {
+        // Get the date range chosen by the user, or None if it is invalid
+        let imp = imp::FurReport::from_instance(self);
+        imp.format_error.set_visible(false);
+        imp.start_end_error.set_visible(false);
+
+        let active_range = imp.range_combo.active_id().unwrap();
+        let today = Local::today();
+        let range_start_date: Date<Local>;
+        let mut range_end_date = today;
+        if active_range == "week_item" {
+            range_start_date = today - Duration::days(6);
+        } else if active_range == "month_item" {
+            let days_ago = today.day() - 1;
+            range_start_date = today - Duration::days(days_ago.into());
+        } else if active_range == "30_days_item" {
+            range_start_date = today - Duration::days(30);
+        } else if active_range == "six_months_item" {
+            range_start_date = today - Duration::days(180);
+        } else if active_range == "year_item" {
+            range_start_date = today - Duration::days(365);
+        } else {
+            let input_start_date = NaiveDate::parse_from_str(&imp.start_date_entry.text(), "%m/%d/%Y");
+            let input_end_date = NaiveDate::parse_from_str(&imp.end_date_entry.text(), "%m/%d/%Y");
+            // Check if user entered dates properly
+            if input_start_date.is_err() || input_end_date.is_err() {
+                imp.format_error.set_visible(true);
+                return None
+            }
+            // Start date cannot be after end date
+            if (input_end_date.unwrap() - input_start_date.unwrap()).num_days() < 0 {
+                imp.start_end_error.set_visible(true);
+                return None
+            }
+            range_start_date = Local.from_local_date(&input_start_date.unwrap()).unwrap();
+            range_end_date = Local.from_local_date(&input_end_date.unwrap()).unwrap();
+        }
+
+        Some((range_start_date, range_end_date))
+    }
+
+    fn filter_list(&self) -> Vec<String> {
+        // Split the tasks or tags the user chose to filter by
+        let imp = imp::FurReport::from_instance(self);
+        let chosen = imp.filter_entry.text();
+        let mut split_chosen: Vec<&str> = chosen.trim().split(',').collect();
+        // Trim whitespace around each one
+        split_chosen = split_chosen.iter().map(|x| x.trim()).collect();
+        // Don't allow empty ones
+        split_chosen.retain(|&x| !x.trim().is_empty());
+        // Handle duplicates
+        split_chosen = split_chosen.into_iter().unique().collect();
+        // Lowercase them
+        split_chosen.iter().map(|x| x.to_lowercase()).collect()
+    }
+
+    fn tasks_in_range(&self) -> Option<(Date<Local>, Date<Local>, Vec<(database::Task, i64)>)> {
+        // Get all tasks in the chosen date range that match the filter, newest first
+        let imp = imp::FurReport::from_instance(self);
+        let (range_start_date, range_end_date) = self.date_range()?;
+
+        let mut task_list = database::retrieve().unwrap();
+        task_list.reverse();
+
+        let filter_active = imp.filter_check.is_active() && !imp.filter_entry.text().trim().is_empty();
+        let filter_by_tasks = imp.filter_combo.active_id().unwrap() == "tasks_item";
+        let user_chosen = self.filter_list();
+
+        let mut tasks_in_range: Vec<(database::Task, i64)> = Vec::new();
+        for task in task_list {
+            let start = DateTime::parse_from_rfc3339(&task.start_time).unwrap().with_timezone(&Local);
+            let stop = DateTime::parse_from_rfc3339(&task.stop_time).unwrap().with_timezone(&Local);
+            // Check if start time is in date range
+            let start_date = start.date();
+            if start_date < range_start_date || start_date > range_end_date {
+                continue;
+            }
+
+            // Sort by only selected tasks or tags if filter is selected
+            let keep = if !filter_active {
+                true
+            } else if filter_by_tasks {
+                user_chosen.contains(&task.task_name.to_lowercase())
+            } else {
+                // Only keep tasks that contain the user's chosen tags
+                quick_entry::split_tags(&task.tags)
+                    .iter()
+                    .any(|x| user_chosen.contains(x))
+            };
+
+            if keep {
+                let duration = (stop - start).num_seconds() - task.paused;
+                tasks_in_range.push((task, duration));
+            }
+        }
+
+        Some((range_start_date, range_end_date, tasks_in_range))
     }
 }
 
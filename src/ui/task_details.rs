@@ -145,7 +145,7 @@ impl FurTaskDetails {
             task_box.append(&stop);
 
             let total_time = stop_time - start_time;
-            let total_time = total_time.num_seconds();
+            let total_time = total_time.num_seconds() - task.paused;
             let h = total_time / 3600;
             let m = total_time % 3600 / 60;
             let s = total_time % 60;
@@ -159,9 +159,18 @@ impl FurTaskDetails {
             total.set_hexpand(false);
             task_box.append(&total);
 
+            // Show descriptions carried over from other time trackers
+            if !task.description.trim().is_empty() {
+                task_box.set_tooltip_text(Some(&task.description));
+            }
+
             imp.main_box.append(&task_box);
             imp.all_boxes.borrow_mut().push(task_box);
 
+            if task.paused > 0 {
+                self.add_pauses(task.id);
+            }
+
             start.connect_clicked(clone!(@weak self as this => move |_|{
                 let dialog = gtk::MessageDialog::new(
                     Some(&this),
@@ -419,6 +428,27 @@ impl FurTaskDetails {
         }
     }
 
+    fn add_pauses(&self, task_id: i32) {
+        // List the breaks under the times they were taken from
+        let imp = imp::FurTaskDetails::from_instance(self);
+        let time_format = if settings_manager::get_bool("show-seconds") { "%H:%M:%S" } else { "%H:%M" };
+
+        let pauses_box = gtk::Box::new(gtk::Orientation::Vertical, 2);
+        for pause in database::get_pauses(task_id).unwrap_or_default() {
+            let start_time = DateTime::parse_from_rfc3339(&pause.start_time).unwrap();
+            let stop_time = DateTime::parse_from_rfc3339(&pause.stop_time).unwrap();
+            let pause_label = gtk::Label::new(Some(&format!("{} {}–{}",
+                                                            gettext("Paused"),
+                                                            start_time.format(time_format),
+                                                            stop_time.format(time_format))));
+            pause_label.add_css_class("dim-label");
+            pauses_box.append(&pause_label);
+        }
+
+        imp.main_box.append(&pauses_box);
+        imp.all_boxes.borrow_mut().push(pauses_box);
+    }
+
     fn clear_task_list(&self) {
         let imp = imp::FurTaskDetails::from_instance(&self);
 
@@ -24,6 +24,7 @@ use glib::clone;
 use crate::database::Task;
 use crate::ui::{FurTaskDetails, FurtheranceWindow};
 use crate::settings_manager;
+use crate::utils;
 
 
 mod imp {
@@ -143,18 +144,12 @@ impl FurTaskRow {
                 let stop_time = DateTime::parse_from_rfc3339(&task.stop_time).unwrap();
 
                 let duration = stop_time - start_time;
-                *imp.total_time.borrow_mut() += duration.num_seconds();
+                *imp.total_time.borrow_mut() += duration.num_seconds() - task.paused;
             }
         }
         // Format total time to readable string
-        let h = *imp.total_time.borrow() / 3600;
-        let m = *imp.total_time.borrow() % 3600 / 60;
-        let s = *imp.total_time.borrow() % 60;
-        let mut total_time_str = format!("{:02}:{:02}:{:02}", h, m, s);
-
-        if !settings_manager::get_bool("show-seconds") {
-            total_time_str = format!("{:02}:{:02}", h, m);
-        }
+        let total_time_str = utils::format_duration(*imp.total_time.borrow(),
+                                                    settings_manager::get_bool("show-seconds"));
         // Display task's total time
         imp.total_time_label.set_text(&total_time_str);
     }
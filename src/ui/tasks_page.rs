@@ -19,11 +19,12 @@ use adw::prelude::{PreferencesPageExt, PreferencesGroupExt};
 use gettextrs::*;
 use gtk::subclass::prelude::*;
 use gtk::{glib, prelude::*};
-use chrono::{DateTime, Local, Duration};
+use chrono::{Local, Duration};
 
 use crate::ui::FurTasksGroup;
 use crate::database;
 use crate::settings_manager;
+use crate::utils;
 
 mod imp {
     use super::*;
@@ -92,78 +93,32 @@ impl FurTasksPage {
 
         // Reversing chronological order of tasks_list
         tasks_list.reverse();
-        let mut uniq_date_list: Vec<String> = Vec::new();
-        let mut same_date_list: Vec<database::Task> = Vec::new();
-        let mut tasks_sorted_by_day: Vec<Vec<database::Task>> = Vec::new();
-
-        // Go through tasks list and look at all dates
-        let mut i: u32 = 1;
-        let len = tasks_list.len() as u32;
-        for task in tasks_list {
-            let task_clone = task.clone();
-            let date = DateTime::parse_from_rfc3339(&task.start_time).unwrap();
-            let date = date.format("%h %e").to_string();
-            if !uniq_date_list.contains(&date) {
-                // if same_date_list is empty, push "date" to it
-                // if it is not empty, push it to a vec of vecs, and then clear it
-                // and push "date" to it
-                if same_date_list.is_empty() {
-                    same_date_list.push(task_clone);
-                } else {
-                    tasks_sorted_by_day.push(same_date_list.clone());
-                    same_date_list.clear();
-                    same_date_list.push(task_clone);
-                }
-                uniq_date_list.push(date);
-            } else {
-                // otherwise push the task to the list of others with the same date
-                same_date_list.push(task_clone);
-            }
-            // If this is the last iteration, push the list of objects to sorted_by_day
-            if i == len {
-                tasks_sorted_by_day.push(same_date_list.clone());
-            }
-            i += 1;
-            if settings_manager::get_bool("limit-tasks") {
-                if uniq_date_list.len() > settings_manager::get_int("limit-days") as usize {
-                    if same_date_list.len() > 0 && i != len {
-                        tasks_sorted_by_day.push(same_date_list.clone());
-                    }
-                    uniq_date_list.pop();
-                    break;
-                }
-            }
+        let mut tasks_sorted_by_day = utils::group_tasks_by_day(tasks_list);
+        if settings_manager::get_bool("limit-tasks") {
+            tasks_sorted_by_day.truncate(settings_manager::get_int("limit-days") as usize);
         }
 
         // Create FurTasksGroups for all unique days
-        let now = Local::now();
-        let yesterday = now - Duration::days(1);
-        let yesterday = yesterday.format("%h %e").to_string();
-        let today = now.format("%h %e").to_string();
-        for i in 0..uniq_date_list.len() {
+        let today = Local::today().naive_local();
+        let yesterday = today - Duration::days(1);
+        for (date, tasks) in tasks_sorted_by_day {
             let group = FurTasksGroup::new();
-            if uniq_date_list[i] == today {
+            if date == today {
                 group.set_title(&gettext("Today"));
-            } else if uniq_date_list[i] == yesterday{
+            } else if date == yesterday {
                 group.set_title(&gettext("Yesterday"));
             } else {
-                group.set_title(&uniq_date_list[i]);
+                group.set_title(&date.format("%h %e").to_string());
             }
 
             self.add(&group);
-            group.add_task_model(tasks_sorted_by_day[i].clone());
+            group.add_task_model(tasks);
 
             // Set total time for each day
             if settings_manager::get_bool("show-daily-sums") {
                 let day_total_time = group.get_total_day_time();
-                // Format total time to readable string
-                let h = day_total_time / 3600;
-                let m = day_total_time % 3600 / 60;
-                let s = day_total_time % 60;
-                let mut total_time_str = format!("{:02}:{:02}:{:02}", h, m, s);
-                if !settings_manager::get_bool("show-seconds") {
-                    total_time_str = format!("{:02}:{:02}", h, m);
-                }
+                let total_time_str = utils::format_duration(day_total_time,
+                                                            settings_manager::get_bool("show-seconds"));
                 group.set_description(Some(&total_time_str));
             }
 
@@ -22,21 +22,29 @@ use gtk::{gio, glib, CompositeTemplate};
 use glib::{clone, timeout_add_local};
 use std::time::Duration;
 use std::sync::Mutex;
-use std::rc::Rc;
-use std::cell::RefCell;
 use chrono::{DateTime, Local, NaiveDateTime, ParseError, Duration as ChronDur, offset::TimeZone};
-use dbus::blocking::Connection;
 use itertools::Itertools;
-use std::fs::{File, create_dir_all, remove_file};
-use std::io::{self, BufWriter, Write, BufReader, BufRead};
-use directories::ProjectDirs;
-use std::path::PathBuf;
 
 use crate::ui::FurHistoryBox;
 use crate::FurtheranceApplication;
+use crate::autosave::{self, Autosave};
 use crate::database;
+use crate::dbus_service;
+use crate::hooks::{self, Hook};
+use crate::idle;
+use crate::quick_entry::{self, QuickEntry};
 use crate::settings_manager;
+use crate::timer::{TimerState, Tick};
 use crate::config;
+use crate::utils;
+
+// How many tasks the Recent Tasks menu shows
+const RECENT_TASKS: usize = 5;
+
+// Idle dialog answers besides Stop (Reject) and Continue (Accept)
+pub const IDLE_DISCARD: gtk::ResponseType = gtk::ResponseType::Other(1);
+pub const IDLE_SPLIT: gtk::ResponseType = gtk::ResponseType::Other(2);
+pub const IDLE_SAVE_AS_ENTRY: gtk::ResponseType = gtk::ResponseType::Other(3);
 
 mod imp {
     use super::*;
@@ -48,26 +56,49 @@ mod imp {
         #[template_child]
         pub header_bar: TemplateChild<adw::HeaderBar>,
         #[template_child]
+        pub window_title: TemplateChild<adw::WindowTitle>,
+        #[template_child]
         pub add_task: TemplateChild<gtk::Button>,
         #[template_child]
         pub watch: TemplateChild<gtk::Label>,
         #[template_child]
         pub task_input: TemplateChild<gtk::Entry>,
         #[template_child]
+        pub parse_preview: TemplateChild<gtk::Label>,
+        #[template_child]
+        pub start_time_button: TemplateChild<gtk::Button>,
+        #[template_child]
+        pub pause_button: TemplateChild<gtk::Button>,
+        #[template_child]
         pub start_button: TemplateChild<gtk::Button>,
         #[template_child]
         pub history_box: TemplateChild<FurHistoryBox>,
         #[template_child]
         pub toast_overlay: TemplateChild<adw::ToastOverlay>,
+        #[template_child]
+        pub recent_tasks_menu: TemplateChild<gio::Menu>,
 
         pub stored_idle: Mutex<u64>,
         pub idle_notified: Mutex<bool>,
         pub idle_time_reached: Mutex<bool>,
         pub subtract_idle: Mutex<bool>,
         pub idle_start_time: Mutex<String>,
+        // When the computer went to sleep or the screen was locked
+        pub away_since: Mutex<Option<DateTime<Local>>>,
         pub running: Mutex<bool>,
         pub pomodoro_continue: Mutex<bool>,
+        pub resume_start_time: Mutex<Option<DateTime<Local>>>,
+        // Continuing an entry from before the app quit, which already started
+        pub restoring_timer: Mutex<bool>,
+        pub tracked_start_time: Mutex<Option<String>>,
+        pub timer: Mutex<TimerState>,
+        pub running_task_text: Mutex<String>,
+        pub timer_generation: Mutex<u32>,
+        pub pomodoro_count: Mutex<i32>,
+        pub entry_pomodoros: Mutex<i32>,
+        pub db_monitor: Mutex<Option<gio::FileMonitor>>,
         pub idle_dialog: Mutex<gtk::MessageDialog>,
+        pub close_confirmed: Mutex<bool>,
     }
 
     #[glib::object_subclass]
@@ -91,11 +122,21 @@ mod imp {
             obj.setup_widgets();
             obj.setup_signals();
             obj.setup_settings();
+            obj.setup_database_monitor();
             self.parent_constructed(obj);
         }
     }
     impl WidgetImpl for FurtheranceWindow {}
-    impl WindowImpl for FurtheranceWindow {}
+    impl WindowImpl for FurtheranceWindow {
+        fn close_request(&self, window: &Self::Type) -> gtk::Inhibit {
+            // Ask whether a running timer should stop with the window
+            if *self.running.lock().unwrap() && !*self.close_confirmed.lock().unwrap() {
+                window.confirm_close();
+                return gtk::Inhibit(true);
+            }
+            self.parent_close_request(window)
+        }
+    }
     impl ApplicationWindowImpl for FurtheranceWindow {}
     impl AdwApplicationWindowImpl for FurtheranceWindow {}
 }
@@ -123,7 +164,11 @@ impl FurtheranceWindow {
         // Update watch time while timer is running
         let imp = imp::FurtheranceWindow::from_instance(self);
         imp.watch.set_text(text);
-        if settings_manager::get_bool("notify-of-idle") {
+        if *imp.running.lock().unwrap() {
+            dbus_service::tick(imp.timer.lock().unwrap().elapsed(Local::now()).num_seconds());
+        }
+        // Nobody has to be at the computer during a pause or a break
+        if settings_manager::get_bool("notify-of-idle") && *imp.running.lock().unwrap() && !self.is_paused() {
             self.check_user_idle();
         }
     }
@@ -138,16 +183,65 @@ impl FurtheranceWindow {
             *imp.subtract_idle.lock().unwrap() = false;
         }
 
+        let tracked = imp.tracked_start_time.lock().unwrap().take().is_some();
+        let pauses = imp.timer.lock().unwrap().pauses().to_vec();
         let (task_name, tag_list) = self.split_tags_and_task();
-        let _ = database::db_write(&task_name, start_time, stop_time, tag_list);
+        let entry = quick_entry::parse(&imp.task_input.text()).unwrap_or_default();
+        let task = database::Task {
+            id: 0,
+            task_name,
+            start_time: start_time.to_rfc3339(),
+            stop_time: stop_time.to_rfc3339(),
+            tags: tag_list,
+            description: String::new(),
+            pomodoros: std::mem::take(&mut *imp.entry_pomodoros.lock().unwrap()),
+            project: entry.project.unwrap_or_default(),
+            billable: entry.billable,
+            paused: 0,
+        };
+        if tracked {
+            // Saved and cleared together, or skipped if the command line stopped and saved it already
+            let _ = database::finish_running(&task, &pauses);
+        } else {
+            let _ = database::write_entry(&task, &pauses);
+        }
+        *imp.timer.lock().unwrap() = TimerState::Idle;
+        let paused: i64 = pauses.iter().map(|(pause_start, pause_stop)| (*pause_stop - *pause_start).num_seconds()).sum();
+        let duration = (stop_time - start_time).num_seconds() - paused;
+        dbus_service::timer_stopped(imp.task_input.text().trim(), duration);
+        let mut entry = hooks::entry(&imp.task_input.text(), start_time, Some(stop_time));
+        entry["duration"] = duration.into();
+        hooks::run(Hook::Stop, entry);
         imp.task_input.set_text("");
-        imp.history_box.create_tasks_page();
+        self.reset_history_box();
         self.reset_idle();
     }
 
     pub fn reset_history_box(&self) {
         let imp = imp::FurtheranceWindow::from_instance(self);
         imp.history_box.create_tasks_page();
+        self.update_recent_tasks();
+    }
+
+    fn update_recent_tasks(&self) {
+        // The last few distinct tasks, to start again from the menu
+        let imp = imp::FurtheranceWindow::from_instance(self);
+        imp.recent_tasks_menu.remove_all();
+        for task_text in FurtheranceWindow::recent_tasks(RECENT_TASKS) {
+            let item = gio::MenuItem::new(Some(&task_text), None);
+            item.set_action_and_target_value(Some("app.start-task"), Some(&task_text.to_variant()));
+            imp.recent_tasks_menu.append_item(&item);
+        }
+    }
+
+    pub fn recent_tasks(count: usize) -> Vec<String> {
+        let mut task_list = database::retrieve().unwrap_or_default();
+        task_list.sort_by(|a, b| b.start_time.cmp(&a.start_time));
+        task_list.into_iter()
+            .map(|task| quick_entry::task_text(&task))
+            .unique()
+            .take(count)
+            .collect()
     }
 
     fn setup_widgets(&self) {
@@ -173,137 +267,121 @@ impl FurtheranceWindow {
         imp.start_button.set_sensitive(false);
         imp.start_button.add_css_class("suggested-action");
         self.refresh_timer();
+        self.update_recent_tasks();
         imp.task_input.grab_focus();
 
-        if settings_manager::get_bool("autosave") {
-            self.check_for_autosave();
-        }
+        self.check_for_autosave();
     }
 
     fn setup_signals(&self) {
         let imp = imp::FurtheranceWindow::from_instance(self);
         *imp.running.lock().unwrap() = false;
-        let start_time = Rc::new(RefCell::new(Local::now()));
-        let stop_time = Rc::new(RefCell::new(Local::now()));
 
         imp.task_input.connect_changed(clone!(@weak self as this => move |task_input| {
             let imp2 = imp::FurtheranceWindow::from_instance(&this);
-            let task_input_text = task_input.text();
-            let split_tags: Vec<&str> = task_input_text.trim().split("#").collect();
-            if split_tags[0].trim().is_empty() {
-                imp2.start_button.set_sensitive(false);
+            let is_valid = quick_entry::parse(&task_input.text()).is_ok();
+            this.update_parse_preview();
+            if *imp2.running.lock().unwrap() {
+                // The running task can always be stopped, invalid changes are undone
+                if is_valid {
+                    task_input.remove_css_class("error");
+                } else {
+                    task_input.add_css_class("error");
+                }
             } else {
-                imp2.start_button.set_sensitive(true);
+                imp2.start_button.set_sensitive(is_valid);
             }
         }));
 
         imp.start_button.connect_clicked(clone!(@weak self as this => move |button| {
             let imp2 = imp::FurtheranceWindow::from_instance(&this);
             if !*imp2.running.lock().unwrap() {
-                if settings_manager::get_bool("pomodoro") && !*imp2.pomodoro_continue.lock().unwrap() {
-                    let pomodoro_time = settings_manager::get_int("pomodoro-time");
-                    let mut secs: i32 = 0;
-                    let mut mins: i32 = pomodoro_time;
-                    let mut hrs: i32 = mins / 60;
-                    mins = mins % 60;
-
-                    *imp2.running.lock().unwrap() = true;
-                    *start_time.borrow_mut() = Local::now();
-                    let timer_start = *start_time.borrow();
-                    imp2.task_input.set_sensitive(false);
-                    let duration = Duration::new(1,0);
-                    timeout_add_local(duration, clone!(@strong this as this_clone => move || {
-                        let imp3 = imp::FurtheranceWindow::from_instance(&this_clone);
-                        if *imp3.running.lock().unwrap() {
-                            secs -= 1;
-                            if secs < 0 {
-                                secs = 59;
-                                mins -= 1;
-                                if mins < 0 {
-                                    mins = 59;
-                                    hrs -= 1;
-                                }
-                            }
-                            let watch_text: &str = &format!("{:02}:{:02}:{:02}", hrs, mins, secs).to_string();
-                            this_clone.set_watch_time(watch_text);
-                        }
-                        if settings_manager::get_bool("autosave") {
-                            let autosave_mins = settings_manager::get_int("autosave-time");
-                            let total_elapsed = (pomodoro_time * 60) - (hrs * 3600) - (mins * 60) - secs;
-                            if total_elapsed % (autosave_mins * 60) == 0 {
-                                this_clone.write_autosave(timer_start);
-                            }
-                        }
-                        if hrs == 0 && mins == 0 && secs == 0 {
-                            let timer_stop = Local::now();
-                            *imp3.running.lock().unwrap() = false;
-                            this_clone.pomodoro_over(timer_start, timer_stop);
-                        }
-                        Continue(*imp3.running.lock().unwrap())
-                    }));
-                } else {
-                    let mut secs: u32 = 0;
-                    let mut mins: u32 = 0;
-                    let mut hrs: u32 = 0;
-
-                    if *imp2.pomodoro_continue.lock().unwrap() {
-                        let pomodoro_start_time = *start_time.borrow();
-                        let now_time = Local::now();
-                        let continue_time = now_time - pomodoro_start_time;
-                        let continue_time = continue_time.num_seconds() as u32;
-                        hrs = continue_time / 3600;
-                        mins = continue_time % 3600 / 60;
-                        secs = continue_time % 60;
-                        let watch_text: &str = &format!("{:02}:{:02}:{:02}", hrs, mins, secs).to_string();
-                        this.set_watch_time(watch_text);
-
-                        *imp2.pomodoro_continue.lock().unwrap() = false;
-                    } else {
-                        *start_time.borrow_mut() = Local::now();
+                let entry = match quick_entry::parse(&imp2.task_input.text()) {
+                    Ok(entry) => entry,
+                    Err(err) => {
+                        this.display_toast(&err.to_string());
+                        return;
                     }
-
-                    *imp2.running.lock().unwrap() = true;
-                    imp2.task_input.set_sensitive(false);
-                    let autosave_start = *start_time.borrow();
-                    let duration = Duration::new(1,0);
-                    timeout_add_local(duration, clone!(@strong this as this_clone => move || {
-                        let imp3 = imp::FurtheranceWindow::from_instance(&this_clone);
-                        if *imp3.running.lock().unwrap() {
-                            secs += 1;
-                            if secs > 59 {
-                                secs = 0;
-                                mins += 1;
-                                if mins > 59 {
-                                    mins = 0;
-                                    hrs += 1;
-                                }
-                            }
-                            let watch_text: &str = &format!("{:02}:{:02}:{:02}", hrs, mins, secs).to_string();
-                            this_clone.set_watch_time(watch_text);
-
-                            if settings_manager::get_bool("autosave") {
-                                let autosave_mins = settings_manager::get_int("autosave-time") as u32;
-                                let total_elapsed = (hrs * 3600) + (mins * 60) + secs;
-                                if total_elapsed % (autosave_mins * 60) == 0 {
-                                    this_clone.write_autosave(autosave_start);
-                                }
-                            }
-                        }
-                        Continue(*imp3.running.lock().unwrap())
-                    }));
+                };
+                if let Some(length) = entry.logged {
+                    this.log_entry(&entry, length);
+                    return;
                 }
+                // Start and stop times are only typed in once
+                imp2.task_input.set_text(&entry.task_text());
+
+                // Continue counting up from a timer started elsewhere, or after a pomodoro
+                let mut continue_start_time = imp2.resume_start_time.lock().unwrap().take();
+                let mut continue_pauses = Vec::new();
+                if *imp2.pomodoro_continue.lock().unwrap() {
+                    let pomodoro = imp2.timer.lock().unwrap().clone();
+                    continue_start_time = continue_start_time.or_else(|| pomodoro.start_time());
+                    // Idle time left out of the pomodoro stays left out
+                    continue_pauses = pomodoro.pauses().to_vec();
+                    *imp2.pomodoro_continue.lock().unwrap() = false;
+                }
+
+                let start_time = Local::now() - entry.start_offset.unwrap_or_else(ChronDur::zero);
+                let timer = match continue_start_time {
+                    Some(start_time) => TimerState::Running { start_time, pauses: continue_pauses },
+                    None if settings_manager::get_bool("pomodoro") => {
+                        let pomodoro_time = settings_manager::get_int("pomodoro-time") as i64;
+                        TimerState::pomodoro(start_time, ChronDur::minutes(pomodoro_time))
+                    }
+                    None => TimerState::running(start_time),
+                };
+                let timer_start = timer.start_time().unwrap();
+                // Pomodoros count down without breaks
+                imp2.pause_button.set_visible(matches!(timer, TimerState::Running { .. }));
+                *imp2.timer.lock().unwrap() = timer;
+                *imp2.running.lock().unwrap() = true;
+                this.set_editing_running_task(true);
+                this.update_pomodoro_title();
+                this.start_ticking();
                 button.set_icon_name("media-playback-stop-symbolic");
+                this.start_running(timer_start);
             } else {
-                *stop_time.borrow_mut() = Local::now();
+                // Don't save the task without a name or with a typo in it
+                if quick_entry::parse(&imp2.task_input.text()).is_err() {
+                    let running_task_text = imp2.running_task_text.lock().unwrap().clone();
+                    imp2.task_input.set_text(&running_task_text);
+                }
+                let timer = imp2.timer.lock().unwrap().clone();
+                // A paused task ended when it was paused
+                let stop_time = match timer {
+                    TimerState::Paused { paused_at, .. } => paused_at,
+                    _ => Local::now(),
+                };
+                let start_time = timer.start_time().unwrap_or(stop_time);
                 *imp2.running.lock().unwrap() = false;
+                imp2.pause_button.set_visible(false);
+                this.reset_pause_button();
                 button.set_icon_name("media-playback-start-symbolic");
                 this.refresh_timer();
-                imp2.task_input.set_sensitive(true);
-                this.save_task(*start_time.borrow(), *stop_time.borrow());
-                FurtheranceWindow::delete_autosave();
+                this.set_editing_running_task(false);
+                this.save_task(start_time, stop_time);
+                autosave::delete();
             }
         }));
 
+        imp.pause_button.connect_clicked(clone!(@weak self as this => move |_| {
+            this.toggle_pause();
+        }));
+
+        imp.start_time_button.connect_clicked(clone!(@weak self as this => move |_| {
+            this.edit_start_time();
+        }));
+
+        // Edits to the running task are saved with Enter or when leaving the field
+        imp.task_input.connect_activate(clone!(@weak self as this => move |_| {
+            this.update_running_task();
+        }));
+        let focus_controller = gtk::EventControllerFocus::new();
+        focus_controller.connect_leave(clone!(@weak self as this => move |_| {
+            this.update_running_task();
+        }));
+        imp.task_input.add_controller(&focus_controller);
+
         imp.add_task.connect_clicked(clone!(@weak self as this => move |_| {
             let dialog = gtk::MessageDialog::new(
                 Some(&this),
@@ -483,6 +561,370 @@ impl FurtheranceWindow {
         }));
     }
 
+    fn start_ticking(&self) {
+        // Redraw the watch every second from the timer's start time
+        let imp = imp::FurtheranceWindow::from_instance(self);
+        let mut last_autosave = Local::now();
+        // Only the latest timer keeps ticking
+        let generation = {
+            let mut timer_generation = imp.timer_generation.lock().unwrap();
+            *timer_generation += 1;
+            *timer_generation
+        };
+        let mut tick = clone!(@strong self as this => move || {
+            let imp = imp::FurtheranceWindow::from_instance(&this);
+            if *imp.timer_generation.lock().unwrap() != generation {
+                return Continue(false);
+            }
+            let now = Local::now();
+            let timer = imp.timer.lock().unwrap().clone();
+            // The start time can change while the timer runs
+            let timer_start = timer.start_time().unwrap_or(now);
+            match timer.tick(now) {
+                Some(Tick::Watch(seconds)) => this.set_watch_time(&utils::format_duration(seconds, true)),
+                Some(Tick::PomodoroOver) => {
+                    this.set_watch_time(&utils::format_duration(0, true));
+                    *imp.running.lock().unwrap() = false;
+                    this.pomodoro_over(timer_start, now);
+                    return Continue(false);
+                }
+                Some(Tick::BreakOver) => {
+                    this.break_over();
+                    return Continue(false);
+                }
+                None => return Continue(false),
+            }
+
+            let running = *imp.running.lock().unwrap();
+            if settings_manager::get_bool("autosave") && running && !this.is_paused() {
+                let autosave_mins = settings_manager::get_int("autosave-time") as i64;
+                if (now - last_autosave).num_minutes() >= autosave_mins {
+                    this.write_autosave();
+                    last_autosave = now;
+                }
+            }
+            Continue(true)
+        });
+
+        // Show the time right away, then once a second
+        if let Continue(true) = tick() {
+            timeout_add_local(Duration::new(1, 0), tick);
+        }
+    }
+
+    fn set_editing_running_task(&self, running: bool) {
+        // While a task runs, Enter saves changes to it instead of stopping it
+        let imp = imp::FurtheranceWindow::from_instance(self);
+        imp.task_input.set_activates_default(!running);
+        imp.task_input.remove_css_class("error");
+        imp.start_time_button.set_visible(running);
+        *imp.running_task_text.lock().unwrap() = imp.task_input.text().trim().to_string();
+    }
+
+    fn update_running_task(&self) {
+        let imp = imp::FurtheranceWindow::from_instance(self);
+        if !*imp.running.lock().unwrap() {
+            return;
+        }
+        let running_task_text = imp.running_task_text.lock().unwrap().clone();
+        let task_text = match quick_entry::parse(&imp.task_input.text()) {
+            Ok(entry) => entry.task_text(),
+            Err(_) => {
+                imp.task_input.set_text(&running_task_text);
+                return;
+            }
+        };
+        // The running task already has its start time
+        if task_text != imp.task_input.text().trim() {
+            imp.task_input.set_text(&task_text);
+        }
+        if task_text != running_task_text {
+            *imp.running_task_text.lock().unwrap() = task_text;
+            self.save_running_task();
+        }
+    }
+
+    fn update_parse_preview(&self) {
+        // Show how the task input is read, or what is wrong with it
+        let imp = imp::FurtheranceWindow::from_instance(self);
+        let task_input_text = imp.task_input.text();
+        imp.parse_preview.set_visible(!task_input_text.trim().is_empty());
+        match quick_entry::parse(&task_input_text) {
+            Ok(entry) => {
+                imp.parse_preview.remove_css_class("error_message");
+                imp.parse_preview.add_css_class("dim-label");
+                imp.parse_preview.set_text(&FurtheranceWindow::describe_entry(&entry));
+            }
+            Err(err) => {
+                imp.parse_preview.remove_css_class("dim-label");
+                imp.parse_preview.add_css_class("error_message");
+                imp.parse_preview.set_text(&err.to_string());
+            }
+        }
+    }
+
+    fn describe_entry(entry: &QuickEntry) -> String {
+        let mut parts = vec![entry.task_name.clone()];
+        parts.extend(entry.tags.iter().map(|tag| format!("#{}", tag)));
+        if let Some(project) = &entry.project {
+            parts.push(format!("{} {}", gettext("Project"), project));
+        }
+        if entry.billable {
+            parts.push(gettext("Billable"));
+        }
+        let now = Local::now();
+        if let Some(offset) = entry.start_offset {
+            parts.push(format!("{} {} ({} {})",
+                               gettext("Started at"),
+                               (now - offset).format("%H:%M"),
+                               quick_entry::format_length(offset),
+                               gettext("ago")));
+        }
+        if let Some(length) = entry.logged {
+            parts.push(format!("{} {}–{} ({})",
+                               gettext("Logged"),
+                               (now - length).format("%H:%M"),
+                               now.format("%H:%M"),
+                               quick_entry::format_length(length)));
+        }
+        parts.join(" · ")
+    }
+
+    fn log_entry(&self, entry: &QuickEntry, length: ChronDur) {
+        // Save a finished entry that ends now, without starting the timer
+        let imp = imp::FurtheranceWindow::from_instance(self);
+        let stop_time = Local::now();
+        if database::write_entry(&entry.to_task(stop_time - length, stop_time), &[]).is_ok() {
+            imp.task_input.set_text("");
+            self.reset_history_box();
+            self.display_toast(&format!("{} {}", gettext("Logged"), entry.task_name));
+        } else {
+            self.display_toast(&gettext("Could not save the entry."));
+        }
+    }
+
+    fn save_running_task(&self) {
+        // Carry changes to the running task to the command line, D-Bus and the autosave
+        let imp = imp::FurtheranceWindow::from_instance(self);
+        let start_time = match imp.timer.lock().unwrap().start_time() {
+            Some(start_time) => start_time,
+            None => return,
+        };
+        let (task_name, tag_list) = self.split_tags_and_task();
+        let entry = quick_entry::parse(&imp.task_input.text()).unwrap_or_default();
+        let project = entry.project.unwrap_or_default();
+        if database::start_running(&task_name, start_time, &tag_list, &project, entry.billable).is_ok() {
+            *imp.tracked_start_time.lock().unwrap() = Some(start_time.to_rfc3339());
+        }
+        self.save_running_state();
+        dbus_service::running_task_changed(imp.task_input.text().trim());
+        if settings_manager::get_bool("autosave") {
+            self.write_autosave();
+        }
+    }
+
+    fn edit_start_time(&self) {
+        // Move the start of the running task, for work that began before the timer
+        let imp = imp::FurtheranceWindow::from_instance(self);
+        let start_time = match imp.timer.lock().unwrap().start_time() {
+            Some(start_time) => start_time,
+            None => return,
+        };
+        let time_format = if settings_manager::get_bool("show-seconds") {
+            "%h %d %Y %H:%M:%S"
+        } else {
+            "%h %d %Y %H:%M"
+        };
+
+        let dialog = gtk::MessageDialog::new(
+            Some(self),
+            gtk::DialogFlags::MODAL,
+            gtk::MessageType::Question,
+            gtk::ButtonsType::OkCancel,
+            &format!("<span size='x-large' weight='bold'>{}</span>", &gettext("Change Start Time")),
+        );
+        dialog.set_use_markup(true);
+
+        let message_area = dialog.message_area().downcast::<gtk::Box>().unwrap();
+        let vert_box = gtk::Box::new(gtk::Orientation::Vertical, 5);
+        let start_time_edit = gtk::Entry::new();
+        start_time_edit.set_text(&start_time.format(time_format).to_string());
+        start_time_edit.set_activates_default(true);
+
+        let instructions = gtk::Label::new(Some(
+            &gettext("*Use the format MMM DD YYYY HH:MM:SS")));
+        if !settings_manager::get_bool("show-seconds") {
+            instructions.set_text(&gettext("*Use the format MMM DD YYYY HH:MM"));
+        }
+        instructions.set_visible(false);
+        instructions.add_css_class("error_message");
+
+        let future_error = gtk::Label::new(Some(
+            &gettext("*Time cannot be in the future.")));
+        future_error.set_visible(false);
+        future_error.add_css_class("error_message");
+
+        let pause_error = gtk::Label::new(Some(
+            &gettext("*Start time cannot be later than a pause.")));
+        pause_error.set_visible(false);
+        pause_error.add_css_class("error_message");
+
+        vert_box.append(&start_time_edit);
+        vert_box.append(&instructions);
+        vert_box.append(&future_error);
+        vert_box.append(&pause_error);
+        message_area.append(&vert_box);
+        dialog.set_default_response(gtk::ResponseType::Ok);
+
+        dialog.connect_response(clone!(@weak self as this, @strong dialog => move |_, resp| {
+            if resp != gtk::ResponseType::Ok {
+                dialog.close();
+                return;
+            }
+            let imp = imp::FurtheranceWindow::from_instance(&this);
+            instructions.set_visible(false);
+            future_error.set_visible(false);
+            pause_error.set_visible(false);
+
+            let new_start_time = match NaiveDateTime::parse_from_str(&start_time_edit.text(), time_format)
+                .ok()
+                .and_then(|naive| Local.from_local_datetime(&naive).earliest()) {
+                Some(new_start_time) => new_start_time,
+                None => {
+                    instructions.set_visible(true);
+                    return;
+                }
+            };
+            let timer = imp.timer.lock().unwrap().clone();
+            let first_pause = timer.pauses().first().map(|(pause_start, _)| *pause_start);
+            let paused_at = match timer {
+                TimerState::Paused { paused_at, .. } => Some(paused_at),
+                _ => None,
+            };
+            if new_start_time > Local::now() {
+                future_error.set_visible(true);
+            } else if matches!(first_pause.or(paused_at), Some(pause_start) if new_start_time > pause_start) {
+                pause_error.set_visible(true);
+            } else {
+                if *imp.running.lock().unwrap() {
+                    *imp.timer.lock().unwrap() = timer.with_start_time(new_start_time);
+                    this.save_running_task();
+                }
+                dialog.close();
+            }
+        }));
+
+        dialog.show();
+    }
+
+    pub fn toggle_pause(&self) {
+        // Take a break without ending the task
+        let imp = imp::FurtheranceWindow::from_instance(self);
+        let now = Local::now();
+        let timer = imp.timer.lock().unwrap().clone();
+        match timer {
+            TimerState::Running { .. } => {
+                *imp.timer.lock().unwrap() = timer.pause(now);
+                imp.pause_button.set_icon_name("media-playback-start-symbolic");
+                imp.pause_button.set_tooltip_text(Some(&gettext("Resume")));
+            }
+            TimerState::Paused { .. } => {
+                *imp.timer.lock().unwrap() = timer.resume(now);
+                self.reset_pause_button();
+            }
+            _ => {}
+        }
+        self.save_running_state();
+        if settings_manager::get_bool("autosave") {
+            self.write_autosave();
+        }
+    }
+
+    fn is_paused(&self) -> bool {
+        let imp = imp::FurtheranceWindow::from_instance(self);
+        matches!(*imp.timer.lock().unwrap(), TimerState::Paused { .. })
+    }
+
+    fn reset_pause_button(&self) {
+        let imp = imp::FurtheranceWindow::from_instance(self);
+        imp.pause_button.set_icon_name("media-playback-pause-symbolic");
+        imp.pause_button.set_tooltip_text(Some(&gettext("Pause")));
+    }
+
+    pub fn elapsed_time(&self) -> i64 {
+        // Seconds spent on the running task, without pauses
+        let imp = imp::FurtheranceWindow::from_instance(self);
+        imp.timer.lock().unwrap().elapsed(Local::now()).num_seconds()
+    }
+
+    fn setup_database_monitor(&self) {
+        // Pick up tasks that are started, stopped or added from the command line
+        let imp = imp::FurtheranceWindow::from_instance(self);
+        let db_file = gio::File::for_path(database::get_directory());
+        if let Ok(monitor) = db_file.monitor_file(gio::FileMonitorFlags::NONE, gio::Cancellable::NONE) {
+            monitor.connect_changed(clone!(@weak self as this => move |_, _, _, event| {
+                // The app's own writes already updated the window
+                if event == gio::FileMonitorEvent::ChangesDoneHint && database::changed_elsewhere() {
+                    this.sync_running_task();
+                    this.reset_history_box();
+                }
+            }));
+            *imp.db_monitor.lock().unwrap() = Some(monitor);
+        }
+        self.sync_running_task();
+    }
+
+    fn start_running(&self, start_time: DateTime<Local>) {
+        // Let the command line and D-Bus know which task is running
+        let imp = imp::FurtheranceWindow::from_instance(self);
+        let (task_name, tag_list) = self.split_tags_and_task();
+        let entry = quick_entry::parse(&imp.task_input.text()).unwrap_or_default();
+        let project = entry.project.unwrap_or_default();
+        if database::start_running(&task_name, start_time, &tag_list, &project, entry.billable).is_ok() {
+            *imp.tracked_start_time.lock().unwrap() = Some(start_time.to_rfc3339());
+        }
+        self.save_running_state();
+        if std::mem::take(&mut *imp.restoring_timer.lock().unwrap()) {
+            dbus_service::running_task_changed(imp.task_input.text().trim());
+        } else {
+            dbus_service::timer_started(imp.task_input.text().trim(), start_time);
+            hooks::run(Hook::Start, hooks::entry(&imp.task_input.text(), start_time, None));
+        }
+    }
+
+    fn sync_running_task(&self) {
+        let imp = imp::FurtheranceWindow::from_instance(self);
+        let running = match database::get_running() {
+            Ok(running) => running,
+            Err(_) => return,
+        };
+        let tracked = imp.tracked_start_time.lock().unwrap().clone();
+
+        let timer_running = *imp.running.lock().unwrap();
+        match running {
+            // Started from the command line, or left running when the window closed
+            Some(running) if Some(&running.start_time) != tracked.as_ref() && !timer_running => {
+                match autosave::deserialize(&running.state) {
+                    // With its pauses and pomodoros
+                    Some(saved) if running.keep_running => self.restore_timer(&saved),
+                    // The app crashed, check_for_autosave asks what to do with it
+                    Some(_) => {}
+                    None => {
+                        if let Ok(start_time) = DateTime::parse_from_rfc3339(&running.start_time) {
+                            let task_text = utils::task_text(&running.task_name, &running.tags);
+                            self.resume_task(&task_text, start_time.with_timezone(&Local));
+                        }
+                    }
+                }
+            }
+            // Stopped and saved from the command line
+            None if tracked.is_some() && timer_running => {
+                imp.start_button.emit_clicked();
+            }
+            _ => {}
+        }
+    }
+
     fn setup_settings(&self) {
         let imp = imp::FurtheranceWindow::from_instance(self);
         self.reset_idle();
@@ -493,24 +935,16 @@ impl FurtheranceWindow {
         imp.task_input.set_activates_default(true);
     }
 
-    fn get_idle_time(&self) -> Result<u64, Box<dyn std::error::Error>> {
-        let c = Connection::new_session()?;
-
-        let p = c.with_proxy("org.gnome.Mutter.IdleMonitor",
-            "/org/gnome/Mutter/IdleMonitor/Core",
-            Duration::from_millis(5000)
-        );
-        let (idle_time,): (u64,) = p.method_call("org.gnome.Mutter.IdleMonitor", "GetIdletime", ())?;
-
-        Ok(idle_time / 1000)
-    }
-
     fn check_user_idle(&self) {
         let imp = imp::FurtheranceWindow::from_instance(self);
+        // Idle time is wrong while the screen is locked or just after a suspend
+        if imp.away_since.lock().unwrap().is_some() {
+            return;
+        }
         // Check for user idle
-        let idle_time = match self.get_idle_time() {
-            Ok(val) => val,
-            Err(_) => 1,
+        let idle_time = match idle::idle_time() {
+            Some(val) => val,
+            None => return,
         };
         // If user was idle and has now returned...
         if idle_time < (settings_manager::get_int("idle-time") * 60) as u64
@@ -533,6 +967,45 @@ impl FurtheranceWindow {
         }
     }
 
+    pub fn system_went_away(&self, away_since: DateTime<Local>) {
+        // The computer is about to sleep or the screen was locked
+        let imp = imp::FurtheranceWindow::from_instance(self);
+        if !*imp.running.lock().unwrap() || imp.away_since.lock().unwrap().is_some() {
+            return;
+        }
+        *imp.away_since.lock().unwrap() = Some(away_since);
+        if settings_manager::get_bool("autosave") {
+            self.write_autosave();
+        }
+    }
+
+    pub fn system_came_back(&self) {
+        // Ask about the time away like any other idle time, if it was long enough
+        let imp = imp::FurtheranceWindow::from_instance(self);
+        let away_since = match imp.away_since.lock().unwrap().take() {
+            Some(away_since) => away_since,
+            None => return,
+        };
+        if !settings_manager::get_bool("notify-of-idle")
+            || !*imp.running.lock().unwrap()
+            || self.is_paused()
+            || *imp.idle_notified.lock().unwrap() {
+            return;
+        }
+
+        // The user may have been idle for a while before that
+        if !*imp.idle_time_reached.lock().unwrap() {
+            let idle_time = (settings_manager::get_int("idle-time") * 60) as i64;
+            if (Local::now() - away_since).num_seconds() < idle_time {
+                return;
+            }
+            *imp.idle_time_reached.lock().unwrap() = true;
+            *imp.idle_start_time.lock().unwrap() = away_since.to_rfc3339();
+        }
+        *imp.idle_notified.lock().unwrap() = true;
+        self.resume_from_idle();
+    }
+
     fn resume_from_idle(&self) {
         let imp = imp::FurtheranceWindow::from_instance(self);
 
@@ -541,13 +1014,18 @@ impl FurtheranceWindow {
         let idle_start = idle_start.with_timezone(&Local);
         let idle_time = resume_time - idle_start;
         let idle_time = idle_time.num_seconds();
-        let h = idle_time / 60 / 60;
-        let m = (idle_time / 60) - (h * 60);
-        let s = idle_time - (m * 60);
-        let idle_time_str = format!("{}{:02}:{:02}:{:02}", gettext("You have been idle for "), h, m, s);
-        let question_str = gettext("\nWould you like to discard that time, or continue the clock?");
+        let idle_time_str = format!("{}{}", gettext("You have been idle for "), utils::format_duration(idle_time, true));
+        let question_str = gettext("\nWhat should happen to that time?");
         let idle_time_msg = format!("{}{}", idle_time_str, question_str);
 
+        let timer_start_time = imp.timer.lock().unwrap().start_time();
+        if let Some(timer_start_time) = timer_start_time {
+            let mut entry = hooks::entry(&imp.task_input.text(), timer_start_time, None);
+            entry["idle_start_time"] = idle_start.to_rfc3339().into();
+            entry["idle_seconds"] = idle_time.into();
+            hooks::run(Hook::Idle, entry);
+        }
+
         let dialog = gtk::MessageDialog::with_markup(
             Some(self),
             gtk::DialogFlags::MODAL,
@@ -555,34 +1033,99 @@ impl FurtheranceWindow {
             gtk::ButtonsType::None,
             Some(&format!("<span size='x-large' weight='bold'>{}</span>", &gettext("Idle"))),
         );
-        dialog.add_buttons(&[
-            (&gettext("Discard"), gtk::ResponseType::Reject),
-            (&gettext("Continue"), gtk::ResponseType::Accept)
-        ]);
+        // Splitting would restart a pomodoro as a plain timer
+        let can_split = !matches!(*imp.timer.lock().unwrap(), TimerState::Pomodoro { .. });
+        dialog.add_button(&gettext("Stop"), gtk::ResponseType::Reject);
+        dialog.add_button(&gettext("Discard Idle Time"), IDLE_DISCARD);
+        if can_split {
+            dialog.add_button(&gettext("Split"), IDLE_SPLIT);
+            dialog.add_button(&gettext("Save as Entry"), IDLE_SAVE_AS_ENTRY);
+        }
+        dialog.add_button(&gettext("Continue"), gtk::ResponseType::Accept);
         dialog.set_secondary_text(Some(&idle_time_msg));
 
+        // The name used by Save as Entry
+        let message_area = dialog.message_area().downcast::<gtk::Box>().unwrap();
+        let idle_entry_input = gtk::Entry::new();
+        idle_entry_input.set_text(&gettext("Break"));
+        idle_entry_input.set_placeholder_text(Some(&gettext("Task Name #tags @project")));
+        let name_error = gtk::Label::new(None);
+        name_error.add_css_class("error_message");
+        name_error.hide();
+        idle_entry_input.set_visible(can_split);
+        message_area.append(&idle_entry_input);
+        message_area.append(&name_error);
+
         dialog.connect_response(clone!(
             @weak self as this,
             @strong dialog,
             @strong imp.start_button as start_button => move |_, resp| {
-            if resp == gtk::ResponseType::Reject {
-                this.set_subtract_idle(true);
-                start_button.emit_clicked();
-                dialog.close();
-            } else if resp == gtk::ResponseType::Accept {
-                this.reset_idle();
-                dialog.close();
+            match resp {
+                // Stop the task when the user went away
+                gtk::ResponseType::Reject => {
+                    this.set_subtract_idle(true);
+                    start_button.emit_clicked();
+                }
+                gtk::ResponseType::Accept => this.reset_idle(),
+                IDLE_DISCARD => this.discard_idle_time(idle_start, resume_time),
+                // The notification's buttons answer even when the dialog has none
+                IDLE_SPLIT | IDLE_SAVE_AS_ENTRY if !can_split => return,
+                IDLE_SPLIT => this.split_at_idle(idle_start, resume_time, None),
+                IDLE_SAVE_AS_ENTRY => match quick_entry::parse(&idle_entry_input.text()) {
+                    Ok(entry) => this.split_at_idle(idle_start, resume_time, Some(entry)),
+                    Err(err) => {
+                        name_error.set_text(&err.to_string());
+                        name_error.show();
+                        return;
+                    }
+                },
+                _ => return,
             }
+            dialog.close();
         }));
 
         *imp.idle_dialog.lock().unwrap() = dialog.clone();
         let app = FurtheranceApplication::default();
-        app.system_idle_notification(&idle_time_str, &question_str);
+        app.system_idle_notification(&idle_time_str, &question_str, can_split);
 
         dialog.show();
     }
 
+    fn discard_idle_time(&self, idle_start: DateTime<Local>, idle_stop: DateTime<Local>) {
+        // Keep the timer going without the time the user was away
+        let imp = imp::FurtheranceWindow::from_instance(self);
+        // Running tasks keep it as a pause, so it shows in their details
+        let timer = imp.timer.lock().unwrap().skip(idle_start, idle_stop);
+        *imp.timer.lock().unwrap() = timer;
+        self.save_running_state();
+        self.reset_idle();
+    }
+
+    fn split_at_idle(&self, idle_start: DateTime<Local>, idle_stop: DateTime<Local>, idle_entry: Option<QuickEntry>) {
+        // Save the task up to the idle time, then start it again from when the user came back
+        let imp = imp::FurtheranceWindow::from_instance(self);
+        if let Some(idle_entry) = idle_entry {
+            let _ = database::write_entry(&idle_entry.to_task(idle_start, idle_stop), &[]);
+        }
+        let task_text = imp.task_input.text().to_string();
+        self.set_subtract_idle(true);
+        imp.start_button.emit_clicked();
+        self.resume_task(&task_text, idle_stop);
+    }
+
     fn pomodoro_over(&self, timer_start: DateTime<Local>, timer_stop: DateTime<Local>) {
+        let imp = imp::FurtheranceWindow::from_instance(self);
+        hooks::run(Hook::PomodoroEnd, hooks::entry(&imp.task_input.text(), timer_start, Some(timer_stop)));
+        *imp.entry_pomodoros.lock().unwrap() += 1;
+        *imp.pomodoro_count.lock().unwrap() += 1;
+
+        if settings_manager::get_bool("pomodoro-auto-start-breaks") {
+            imp.idle_dialog.lock().unwrap().close();
+            let long = self.start_break(timer_start, timer_stop);
+            FurtheranceApplication::default().system_break_notification(long);
+            return;
+        }
+
         let dialog = gtk::MessageDialog::with_markup(
             Some(self),
             gtk::DialogFlags::MODAL,
@@ -592,6 +1135,7 @@ impl FurtheranceWindow {
         );
         dialog.add_buttons(&[
             (&gettext("Continue"), gtk::ResponseType::Accept),
+            (&gettext("Take a Break"), gtk::ResponseType::Apply),
             (&gettext("Stop"), gtk::ResponseType::Reject)
         ]);
 
@@ -602,11 +1146,10 @@ impl FurtheranceWindow {
             @strong dialog => move |_, resp| {
             let imp = imp::FurtheranceWindow::from_instance(&this);
             if resp == gtk::ResponseType::Reject {
-                imp.start_button.set_icon_name("media-playback-start-symbolic");
-                this.refresh_timer();
-                imp.task_input.set_sensitive(true);
-                this.save_task(timer_start, timer_stop);
-                this.reset_idle();
+                this.finish_pomodoro(timer_start, timer_stop);
+                dialog.close();
+            } else if resp == gtk::ResponseType::Apply {
+                this.start_break(timer_start, timer_stop);
                 dialog.close();
             } else if resp == gtk::ResponseType::Accept {
                 *imp.pomodoro_continue.lock().unwrap() = true;
@@ -622,105 +1165,259 @@ impl FurtheranceWindow {
         dialog.show();
     }
 
-    fn write_autosave(&self, auto_start_time: DateTime<Local>) {
-        let auto_stop_time = Local::now().to_rfc3339();
-        let auto_start_time = auto_start_time.to_rfc3339();
-        let (task_name, tag_list) = self.split_tags_and_task();
+    fn finish_pomodoro(&self, timer_start: DateTime<Local>, timer_stop: DateTime<Local>) {
+        let imp = imp::FurtheranceWindow::from_instance(self);
+        imp.start_button.set_icon_name("media-playback-start-symbolic");
+        self.refresh_timer();
+        self.set_editing_running_task(false);
+        self.save_task(timer_start, timer_stop);
+        self.reset_idle();
+    }
 
-        let path = FurtheranceWindow::get_autosave_path();
-        let file = File::create(path).expect("Couldn't create autosave file");
-        let mut file = BufWriter::new(file);
+    fn start_break(&self, timer_start: DateTime<Local>, timer_stop: DateTime<Local>) -> bool {
+        // Save the pomodoro and count down a break, returns if it is a long one
+        let imp = imp::FurtheranceWindow::from_instance(self);
+        let task_text = imp.task_input.text();
+        self.finish_pomodoro(timer_start, timer_stop);
+        // Keep the task for the next pomodoro
+        imp.task_input.set_text(&task_text);
+
+        let interval = settings_manager::get_int("pomodoro-long-break-interval").max(1);
+        let long = *imp.pomodoro_count.lock().unwrap() % interval == 0;
+        let break_time = if long {
+            settings_manager::get_int("pomodoro-long-break")
+        } else {
+            settings_manager::get_int("pomodoro-short-break")
+        };
+        *imp.timer.lock().unwrap() = TimerState::take_break(Local::now(), ChronDur::minutes(break_time as i64), long);
+        self.update_pomodoro_title();
+        self.start_ticking();
+        long
+    }
 
-        writeln!(file, "{}", task_name).expect("Unable to write autosave");
-        writeln!(file, "{}", auto_start_time).expect("Unable to write autosave");
-        writeln!(file, "{}", auto_stop_time).expect("Unable to write autosave");
-        write!(file, "{}", tag_list).expect("Unable to write autosave");
+    fn break_over(&self) {
+        let imp = imp::FurtheranceWindow::from_instance(self);
+        *imp.timer.lock().unwrap() = TimerState::Idle;
+        self.refresh_timer();
+        FurtheranceApplication::default().system_break_over_notification();
+        if settings_manager::get_bool("pomodoro-auto-start-pomodoros") && imp.start_button.is_sensitive() {
+            imp.start_button.emit_clicked();
+        }
     }
 
-    fn delete_autosave() {
-        let path = FurtheranceWindow::get_autosave_path();
-        if path.exists() {
-            remove_file(path).expect("Could not delete autosave");
+    pub fn update_pomodoro_title(&self) {
+        // Show where in the cycle of pomodoros and breaks the timer is
+        let imp = imp::FurtheranceWindow::from_instance(self);
+        if !settings_manager::get_bool("pomodoro") {
+            imp.window_title.set_subtitle("");
+            return;
         }
+        let subtitle = match *imp.timer.lock().unwrap() {
+            TimerState::Break { long: true, .. } => gettext("Long Break"),
+            TimerState::Break { .. } => gettext("Short Break"),
+            _ => {
+                let interval = settings_manager::get_int("pomodoro-long-break-interval").max(1);
+                let count = *imp.pomodoro_count.lock().unwrap() % interval + 1;
+                format!("{} {}/{}", gettext("Pomodoro"), count, interval)
+            }
+        };
+        imp.window_title.set_subtitle(&subtitle);
     }
 
-    fn get_autosave_path() -> PathBuf {
-        let mut path = PathBuf::new();
-        if let Some(proj_dirs) = ProjectDirs::from("com", "lakoliu",  "Furtherance") {
-            path = PathBuf::from(proj_dirs.data_dir());
-            create_dir_all(path.clone()).expect("Unable to create autosave directory");
-            path.extend(&["furtherance_autosave.txt"]);
+    fn snapshot(&self) -> Option<Autosave> {
+        // Everything needed to save or continue the task if the app quits
+        let imp = imp::FurtheranceWindow::from_instance(self);
+        let timer = imp.timer.lock().unwrap().clone();
+        timer.start_time()?;
+        Some(Autosave {
+            task_text: imp.task_input.text().trim().to_string(),
+            timer,
+            pomodoros: *imp.entry_pomodoros.lock().unwrap(),
+            pomodoro_count: *imp.pomodoro_count.lock().unwrap(),
+            saved_at: Local::now(),
+        })
+    }
+
+    fn write_autosave(&self) {
+        // In case the app doesn't quit properly, the running task in the database is kept as recent
+        if let Some(saved) = self.snapshot() {
+            let _ = autosave::write(&saved);
+        }
+        self.save_running_state();
+    }
+
+    fn save_running_state(&self) {
+        // Keep the timer with the running task, so it continues when the app opens again
+        let imp = imp::FurtheranceWindow::from_instance(self);
+        let tracked = imp.tracked_start_time.lock().unwrap().clone();
+        if let (Some(start_time), Some(saved)) = (tracked, self.snapshot()) {
+            let _ = database::update_running_state(&start_time,
+                                                   &autosave::serialize(&saved),
+                                                   saved.pomodoros,
+                                                   saved.timer.pauses());
         }
-        path
+    }
+
+    pub fn keep_running(&self) {
+        // Continue the running task the next time the app opens, without asking
+        let imp = imp::FurtheranceWindow::from_instance(self);
+        self.save_running_state();
+        if let Some(start_time) = imp.tracked_start_time.lock().unwrap().clone() {
+            let _ = database::keep_running(&start_time);
+        }
+        // The running task is saved, the autosave would only ask about it again
+        autosave::delete();
+    }
+
+    fn confirm_close(&self) {
+        let dialog = gtk::MessageDialog::new(
+            Some(self),
+            gtk::DialogFlags::MODAL,
+            gtk::MessageType::Question,
+            gtk::ButtonsType::None,
+            &gettext("Stop the Timer?"),
+        );
+        dialog.set_secondary_text(Some(
+            &gettext("A task is still running. If it keeps running, the timer continues when Furtherance is opened again.")));
+        dialog.add_buttons(&[
+            (&gettext("Cancel"), gtk::ResponseType::Cancel),
+            (&gettext("Keep Running"), gtk::ResponseType::Accept),
+            (&gettext("Stop"), gtk::ResponseType::Reject),
+        ]);
+
+        dialog.connect_response(clone!(
+            @weak self as this,
+            @strong dialog => move |_, resp| {
+            let imp = imp::FurtheranceWindow::from_instance(&this);
+            dialog.close();
+            match resp {
+                gtk::ResponseType::Reject => {
+                    imp.start_button.emit_clicked();
+                }
+                gtk::ResponseType::Accept => this.keep_running(),
+                _ => return,
+            }
+            *imp.close_confirmed.lock().unwrap() = true;
+            this.close();
+        }));
+
+        dialog.show();
     }
 
     fn split_tags_and_task(&self) -> (String, String) {
         let imp = imp::FurtheranceWindow::from_instance(self);
-        let task_input_text = imp.task_input.text();
-        let mut split_tags: Vec<&str> = task_input_text.trim().split("#").collect();
-        // Remove task name from tags list
-        let task_name = *split_tags.first().unwrap();
-        split_tags.remove(0);
-        // Trim whitespace around each tag
-        split_tags = split_tags.iter().map(|x| x.trim()).collect();
-        // Don't allow empty tags
-        split_tags.retain(|&x| !x.trim().is_empty());
-        // Handle duplicate tags before they are ever saved
-        split_tags = split_tags.into_iter().unique().collect();
-        // Lowercase tags
-        let lower_tags: Vec<String> = split_tags.iter().map(|x| x.to_lowercase()).collect();
-        let tag_list = lower_tags.join(" #");
-        (task_name.trim().to_string(), tag_list)
+        quick_entry::split_task_text(&imp.task_input.text())
     }
 
     fn check_for_autosave(&self) {
-        let path = FurtheranceWindow::get_autosave_path();
-        if path.exists() {
-            let autosave = FurtheranceWindow::read_autosave().unwrap();
+        // A task the window was timing when the app crashed, from the database or the autosave
+        let running = database::get_running().ok().flatten();
+        if matches!(&running, Some(running) if running.keep_running) {
+            // Closed with "Keep Running", sync_running_task continues it
+            autosave::delete();
+            return;
+        }
+        let crashed = running.as_ref().and_then(|running| autosave::deserialize(&running.state));
+        let from_database = crashed.is_some();
+        let saved = match crashed {
+            Some(saved) => saved,
+            None => {
+                if !settings_manager::get_bool("autosave") || !autosave::exists() {
+                    return;
+                }
+                match autosave::read() {
+                    Some(saved) => saved,
+                    None => {
+                        autosave::delete();
+                        self.display_toast(&gettext("The autosave could not be read."));
+                        return;
+                    }
+                }
+            }
+        };
 
-            database::write_autosave(&autosave[0], &autosave[1], &autosave[2], &autosave[3])
-                .expect("Could not write autosave");
+        // Started from the command line, so it continues instead of being restored
+        let running_start_time = running.as_ref()
+            .and_then(|running| DateTime::parse_from_rfc3339(&running.start_time).ok())
+            .map(|start_time| start_time.with_timezone(&Local));
+        if !from_database && running_start_time == Some(saved.start_time()) {
+            autosave::delete();
+            return;
+        }
 
-            let dialog = gtk::MessageDialog::new(
-                Some(self),
-                gtk::DialogFlags::MODAL,
-                gtk::MessageType::Info,
-                gtk::ButtonsType::Ok,
-                &gettext("Autosave Restored"),
-            );
-            dialog.set_secondary_text(Some(
-                &gettext("Furtherance shut down improperly. An autosave was restored.")
-            ));
+        let time_format = if settings_manager::get_bool("show-seconds") {
+            "%h %d %Y %H:%M:%S"
+        } else {
+            "%h %d %Y %H:%M"
+        };
+        let dialog = gtk::MessageDialog::new(
+            Some(self),
+            gtk::DialogFlags::MODAL,
+            gtk::MessageType::Question,
+            gtk::ButtonsType::None,
+            &gettext("Autosave Found"),
+        );
+        dialog.set_secondary_text(Some(&format!("{}\n\n{}\n{} {}",
+            gettext("Furtherance shut down while a task was running."),
+            saved.task_text,
+            gettext("Last saved"),
+            saved.saved_at.format(time_format))));
+        dialog.add_buttons(&[
+            (&gettext("Discard"), gtk::ResponseType::Reject),
+            (&gettext("Save Entry"), gtk::ResponseType::Apply),
+            (&gettext("Resume Timer"), gtk::ResponseType::Accept),
+        ]);
 
-            dialog.connect_response(clone!(
-                @weak self as this,
-                @strong dialog => move |_, resp| {
-                if resp == gtk::ResponseType::Ok {
+        dialog.connect_response(clone!(
+            @weak self as this,
+            @strong dialog => move |_, resp| {
+            match resp {
+                gtk::ResponseType::Apply => {
+                    // Ended at the last autosave
+                    if from_database {
+                        let _ = database::finish_running(&saved.to_task(), saved.timer.pauses());
+                    } else {
+                        let _ = database::write_entry(&saved.to_task(), saved.timer.pauses());
+                    }
                     this.reset_history_box();
-                    dialog.close();
                 }
-            }));
+                gtk::ResponseType::Accept => this.restore_timer(&saved),
+                gtk::ResponseType::Reject => {
+                    if from_database {
+                        let _ = database::clear_running();
+                    }
+                }
+                _ => return,
+            }
+            autosave::delete();
+            dialog.close();
+        }));
 
-            dialog.show();
-            FurtheranceWindow::delete_autosave();
-        }
+        dialog.show();
     }
 
-    fn read_autosave() -> io::Result<Vec<String>> {
-        let path = FurtheranceWindow::get_autosave_path();
-        let file = File::open(path)?;
-        let reader = BufReader::new(file);
-        let mut vars = Vec::new();
-
-        for line in reader.lines() {
-            vars.push(line?);
+    fn restore_timer(&self, saved: &Autosave) {
+        // Continue the task as if the app never quit
+        let imp = imp::FurtheranceWindow::from_instance(self);
+        if *imp.running.lock().unwrap() {
+            return;
         }
-        // Add empty string if there are no tags
-        if vars.len() == 3 {
-            vars.push("".to_string());
+        *imp.restoring_timer.lock().unwrap() = true;
+        self.resume_task(&saved.task_text, saved.start_time());
+        *imp.restoring_timer.lock().unwrap() = false;
+        if !*imp.running.lock().unwrap() {
+            return;
         }
-
-        Ok(vars)
+        *imp.timer.lock().unwrap() = saved.timer.clone();
+        *imp.entry_pomodoros.lock().unwrap() = saved.pomodoros;
+        *imp.pomodoro_count.lock().unwrap() = saved.pomodoro_count;
+        imp.pause_button.set_visible(!matches!(saved.timer, TimerState::Pomodoro { .. }));
+        if let TimerState::Paused { .. } = saved.timer {
+            imp.pause_button.set_icon_name("media-playback-start-symbolic");
+            imp.pause_button.set_tooltip_text(Some(&gettext("Resume")));
+        }
+        self.update_pomodoro_title();
+        self.save_running_state();
     }
 
     pub fn reset_idle(&self) {
@@ -739,19 +1436,81 @@ impl FurtheranceWindow {
     pub fn duplicate_task(&self, task: database::Task) {
         let imp = imp::FurtheranceWindow::from_instance(self);
         if !*imp.running.lock().unwrap() {
-            let task_text: String;
-            if task.tags.trim().is_empty() {
-                task_text = task.task_name;
-            } else {
-                task_text = format!("{} #{}", task.task_name, task.tags);
-            }
-            imp.task_input.set_text(&task_text);
+            imp.task_input.set_text(&quick_entry::task_text(&task));
             imp.start_button.emit_clicked();
         } else {
             self.display_toast(&gettext("Stop the timer to duplicate a task."));
         }
     }
 
+    pub fn resume_task(&self, task_text: &str, start_time: DateTime<Local>) {
+        let imp = imp::FurtheranceWindow::from_instance(self);
+        if !*imp.running.lock().unwrap() {
+            imp.task_input.set_text(task_text);
+            *imp.resume_start_time.lock().unwrap() = Some(start_time);
+            imp.start_button.emit_clicked();
+        } else {
+            self.display_toast(&gettext("Stop the timer to continue a running task."));
+        }
+    }
+
+    pub fn timer_status(&self) -> Option<(String, DateTime<Local>)> {
+        // The running task and when it started
+        let imp = imp::FurtheranceWindow::from_instance(self);
+        if !*imp.running.lock().unwrap() {
+            return None;
+        }
+        let timer_start_time = imp.timer.lock().unwrap().start_time()?;
+        Some((imp.task_input.text().trim().to_string(), timer_start_time))
+    }
+
+    pub fn start_task(&self, task_text: &str) -> bool {
+        let imp = imp::FurtheranceWindow::from_instance(self);
+        if *imp.running.lock().unwrap() || quick_entry::split_task_text(task_text).0.is_empty() {
+            return false;
+        }
+        imp.task_input.set_text(task_text);
+        imp.start_button.emit_clicked();
+        // Entries like "~1h" are only logged, and a bad offset only shows a toast
+        let running = *imp.running.lock().unwrap();
+        running
+    }
+
+    pub fn stop_task(&self) -> bool {
+        let imp = imp::FurtheranceWindow::from_instance(self);
+        if !*imp.running.lock().unwrap() {
+            return false;
+        }
+        imp.start_button.emit_clicked();
+        true
+    }
+
+    pub fn toggle_timer(&self) -> bool {
+        // Stop the timer, or start the task that was typed in, or else the last one
+        if self.stop_task() {
+            return false;
+        }
+        let imp = imp::FurtheranceWindow::from_instance(self);
+        let task_text = imp.task_input.text().to_string();
+        if quick_entry::split_task_text(&task_text).0.is_empty() {
+            return self.start_last_task();
+        }
+        self.start_task(&task_text)
+    }
+
+    pub fn start_last_task(&self) -> bool {
+        match FurtheranceWindow::recent_tasks(1).first() {
+            Some(task_text) => self.start_task(task_text),
+            None => false,
+        }
+    }
+
+    pub fn add_task(&self) {
+        // Opens the same dialog as the add button
+        let imp = imp::FurtheranceWindow::from_instance(self);
+        imp.add_task.emit_clicked();
+    }
+
     pub fn refresh_timer(&self) {
         let imp = imp::FurtheranceWindow::from_instance(self);
         if settings_manager::get_bool("pomodoro") {
@@ -766,6 +1525,7 @@ impl FurtheranceWindow {
         } else {
             imp.watch.set_text("00:00:00");
         }
+        self.update_pomodoro_title();
     }
 }
 
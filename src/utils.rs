@@ -0,0 +1,196 @@
+// Furtherance - Track your time without being tracked
+// Copyright (C) 2022  Ricky Kresslein <rk@lakoliu.com>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use chrono::{DateTime, NaiveDate};
+use itertools::Itertools;
+
+use crate::database::Task;
+use crate::quick_entry;
+
+// Groups with their total time, each with its own tasks or tags and their time
+pub type GroupedDurations = Vec<(String, i64, Vec<(String, i64)>)>;
+
+pub fn format_duration(total_time: i64, show_seconds: bool) -> String {
+    // Format total time to readable string
+    let h = total_time / 3600;
+    let m = total_time % 3600 / 60;
+    let s = total_time % 60;
+    if show_seconds {
+        format!("{:02}:{:02}:{:02}", h, m, s)
+    } else {
+        format!("{:02}:{:02}", h, m)
+    }
+}
+
+pub fn split_tags_and_task(task_input_text: &str) -> (String, String) {
+    // Split "Task name #tag #other tag" into the task name and its tags
+    let mut split_tags: Vec<&str> = task_input_text.trim().split('#').collect();
+    // Remove task name from tags list
+    let task_name = *split_tags.first().unwrap();
+    split_tags.remove(0);
+    // Trim whitespace around each tag
+    split_tags = split_tags.iter().map(|x| x.trim()).collect();
+    // Don't allow empty tags
+    split_tags.retain(|&x| !x.trim().is_empty());
+    // Handle duplicate tags before they are ever saved
+    split_tags = split_tags.into_iter().unique().collect();
+    // Lowercase tags
+    let lower_tags: Vec<String> = split_tags.iter().map(|x| x.to_lowercase()).collect();
+    let tag_list = lower_tags.join(" #");
+    (task_name.trim().to_string(), tag_list)
+}
+
+pub fn task_text(task_name: &str, tags: &str) -> String {
+    // The task the way it is typed in, with its tags
+    if tags.trim().is_empty() {
+        task_name.to_string()
+    } else {
+        format!("{} #{}", task_name, tags)
+    }
+}
+
+pub fn group_tasks_by_day(tasks: Vec<Task>) -> Vec<(NaiveDate, Vec<Task>)> {
+    // Group sorted tasks by the day they were started, keeping their order
+    let mut tasks_by_day: Vec<(NaiveDate, Vec<Task>)> = Vec::new();
+    for task in tasks {
+        let date = DateTime::parse_from_rfc3339(&task.start_time).unwrap().date().naive_local();
+        match tasks_by_day.last_mut() {
+            Some((day, same_date_list)) if *day == date => same_date_list.push(task),
+            _ => tasks_by_day.push((date, vec![task])),
+        }
+    }
+    tasks_by_day
+}
+
+pub fn durations_by_task(tasks_in_range: Vec<(Task, i64)>) -> GroupedDurations {
+    // Total time of each task and of each set of tags it was tracked with, longest first
+    let mut tasks_by_name: Vec<Vec<(Task, i64)>> = Vec::new();
+    for (task, task_duration) in tasks_in_range {
+        let mut unique = true;
+        for i in 0..tasks_by_name.len() {
+            let (tbn, _) = &tasks_by_name[i][0];
+            if tbn.task_name == task.task_name {
+                tasks_by_name[i].push((task.clone(), task_duration));
+                unique = false;
+            }
+        }
+        if unique {
+            // Add unique task to list for group name
+            let mut new_name_list: Vec<(Task, i64)> = Vec::new();
+            new_name_list.push((task.clone(), task_duration));
+            tasks_by_name.push(new_name_list);
+        }
+    }
+
+    let mut sorted_tasks_by_duration: GroupedDurations = Vec::new();
+    for tbn in tasks_by_name {
+        let mut total_duration: i64 = 0;
+        let mut tags_dur: Vec<(String, i64)> = Vec::new();
+        let task_name = tbn[0].0.task_name.to_string();
+        for tbn_tuple in tbn {
+            let (task, task_duration) = tbn_tuple;
+            total_duration += task_duration;
+
+            let split_tags = quick_entry::split_tags(&task.tags);
+            if !split_tags.is_empty() {
+                let mut formatted_tags = split_tags.join(" #");
+                formatted_tags = format!("#{}", formatted_tags);
+                let mut unique = true;
+                for i in 0..tags_dur.len() {
+                    let (tags, dur) = &tags_dur[i];
+                    if tags == &formatted_tags {
+                        let new_dur = dur + task_duration;
+                        tags_dur[i] = (formatted_tags.clone(), new_dur);
+                        unique = false;
+                    }
+                }
+                if unique {
+                    tags_dur.push((formatted_tags, task_duration))
+                }
+            }
+        }
+
+        // Sort tasks and tags in descending order by duration
+        tags_dur.sort_by_key(|k| k.1);
+        tags_dur.reverse();
+        sorted_tasks_by_duration.push((task_name, total_duration, tags_dur));
+        sorted_tasks_by_duration.sort_by_key(|k| k.1);
+        sorted_tasks_by_duration.reverse();
+    }
+
+    sorted_tasks_by_duration
+}
+
+pub fn durations_by_tag(tasks_in_range: Vec<(Task, i64)>) -> GroupedDurations {
+    // Total time of each tag and of each task tracked with it, longest first
+    let mut tasks_by_tag: Vec<Vec<(String, Task, i64)>> = Vec::new();
+    for (task, task_duration) in tasks_in_range {
+        let mut split_tags = quick_entry::split_tags(&task.tags);
+        // Untagged tasks are grouped together
+        if split_tags.is_empty() {
+            split_tags.push(String::new());
+        }
+        for tag in split_tags {
+            let mut unique = true;
+            for i in 0..tasks_by_tag.len() {
+                let (tbt_tag, _, _) = &tasks_by_tag[i][0];
+                if tbt_tag == &tag {
+                    tasks_by_tag[i].push((tag.to_string(), task.clone(), task_duration));
+                    unique = false;
+                }
+            }
+            if unique {
+                // Add unique task to list for group name
+                let mut new_name_list: Vec<(String, Task, i64)> = Vec::new();
+                new_name_list.push((tag.to_string(), task.clone(), task_duration));
+                tasks_by_tag.push(new_name_list);
+            }
+        }
+    }
+
+    let mut sorted_tasks_by_duration: GroupedDurations = Vec::new();
+    for tbt in tasks_by_tag {
+        let mut total_duration: i64 = 0;
+        let mut tasks_dur: Vec<(String, i64)> = Vec::new();
+        let tag_name = format!("#{}", tbt[0].0);
+        for tbt_tuple in tbt {
+            let (_, task, tag_duration) = tbt_tuple;
+            total_duration += tag_duration;
+
+            let mut unique = true;
+            for i in 0..tasks_dur.len() {
+                let (td_task, dur) = &tasks_dur[i];
+                if td_task == &task.task_name {
+                    let new_dur = dur + tag_duration;
+                    tasks_dur[i] = (task.task_name.clone(), new_dur);
+                    unique = false;
+                }
+            }
+            if unique {
+                tasks_dur.push((task.task_name.clone(), tag_duration))
+            }
+        }
+
+        // Sort tags and tasks in descending order by duration
+        tasks_dur.sort_by_key(|k| k.1);
+        tasks_dur.reverse();
+        sorted_tasks_by_duration.push((tag_name, total_duration, tasks_dur));
+        sorted_tasks_by_duration.sort_by_key(|k| k.1);
+        sorted_tasks_by_duration.reverse();
+    }
+
+    sorted_tasks_by_duration
+}